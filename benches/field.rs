@@ -0,0 +1,121 @@
+//! Compares the bitboard `Field` against the previous `Vec<Vec<Id>>` layout.
+//!
+//! Run with `cargo bench`. This uses a plain timing loop so it can be run on
+//! stable.
+
+extern crate tetrs;
+#[macro_use] extern crate itertools;
+
+use std::time::Instant;
+
+use tetrs::import::*;
+use tetrs::block::Id;
+use tetrs::movegen;
+
+/// The previous field representation, kept for comparison.
+#[derive(Clone)]
+struct NaiveField {
+    width: usize,
+    height: usize,
+    data: Vec<Vec<Id>>
+}
+
+impl NaiveField {
+    fn from_field(field: &Field) -> NaiveField {
+        let data = (0..field.height)
+                       .map(|y| (0..field.width).map(|x| field.get((x, y))).collect())
+                       .collect();
+
+        NaiveField { width: field.width, height: field.height, data }
+    }
+
+    fn clear_lines(&mut self) -> usize {
+        self.data.retain(|x| x.contains(&Id::None));
+
+        let lines = self.height - self.data.len();
+        for _ in 0..lines {
+            self.data.insert(0, vec![Id::None; self.width]);
+        }
+
+        lines
+    }
+
+    fn collides_at_offset(&self, block: &Block, (xo, yo): (i32, i32)) -> bool {
+        block.rs.data(block.id, block.r).iter()
+            .map(|&(dx, dy)| (block.x + dx as i32 + xo, block.y + dy as i32 + yo))
+            .any(|(x, y)| {
+                x < 0 || x as usize >= self.width ||
+                y < 0 || y as usize >= self.height ||
+                self.data[y as usize][x as usize] != Id::None
+            })
+    }
+}
+
+/// Time `f` over `iterations` runs and print the average.
+fn bench<F: FnMut()>(name: &str, iterations: u32, mut f: F) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+
+    let elapsed = start.elapsed();
+    let ns = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+    println!("{:<32} {:>12} ns/iter", name, ns / iterations as u64);
+}
+
+/// A field with a ragged stack and four full rows.
+fn stack() -> Field {
+    let mut field = Field::new();
+
+    for y in 15..field.height {
+        for x in 0..field.width {
+            if y >= field.height - 4 || (x * 7 + y * 3) % 5 != 0 {
+                field.set((x, y), Id::I);
+            }
+        }
+    }
+
+    field
+}
+
+fn main() {
+    let field = stack();
+    let naive = NaiveField::from_field(&field);
+
+    bench("clear_lines (bitboard)", 100_000, || {
+        let mut f = field.clone();
+        assert_eq!(f.clear_lines(), 4);
+    });
+
+    bench("clear_lines (naive)", 100_000, || {
+        let mut f = naive.clone();
+        assert_eq!(f.clear_lines(), 4);
+    });
+
+    let blocks = iproduct!(Id::variants().iter(), Rotation::variants(), -2..10, 0..25)
+                    .map(|(&id, r, x, y)| {
+                        Block::with_options(id, &field, BlockOptions {
+                            x: Some(x), y: Some(y), rotation: r, ..Default::default()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+    bench("collides_at_offset (bitboard)", 1_000, || {
+        let n = blocks.iter().filter(|b| b.collides_at_offset(&field, (0, 1))).count();
+        assert!(n > 0);
+    });
+
+    bench("collides_at_offset (naive)", 1_000, || {
+        let n = blocks.iter().filter(|b| naive.collides_at_offset(b, (0, 1))).count();
+        assert!(n > 0);
+    });
+
+    let wk = wallkick::new("srs").unwrap();
+    let mut empty = field.clone();
+    empty.clear_lines();
+
+    bench("movegen::generate (bitboard)", 1_000, || {
+        let block = Block::new(Id::T, &empty);
+        assert!(!movegen::generate(&empty, &block, &*wk).is_empty());
+    });
+}
@@ -19,16 +19,6 @@ use std::thread;
 use std::time::Duration;
 use std::path::Path;
 
-static COLORMAP: [Color; 7] = [
-    Color::RGB(0, 255, 255), // I
-    Color::RGB(128, 0, 128), // T
-    Color::RGB(255, 165, 0), // L
-    Color::RGB(0, 0, 255),   // J
-    Color::RGB(128, 255, 0), // S
-    Color::RGB(255, 0, 0),   // Z
-    Color::RGB(255, 255, 0)  // O
-];
-
 static KEYMAP: [(Scancode, Action); 9] = [
     (Scancode::Left,  Action::MoveLeft),
     (Scancode::Right, Action::MoveRight),
@@ -41,6 +31,13 @@ static KEYMAP: [(Scancode, Action); 9] = [
     (Scancode::Escape,Action::Quit),
 ];
 
+/// Return the colour of a block, preferring any colour set by the rotation
+/// system.
+fn colour(engine: &Engine, id: block::Id) -> Color {
+    let (r, g, b) = engine.rs.colour(id).unwrap_or_else(|| id.colour());
+    Color::RGB(r, g, b)
+}
+
 fn gather_input(engine: &mut Engine, pump: &mut sdl2::EventPump) {
     engine.co.deactivate_all();
 
@@ -121,13 +118,24 @@ fn main() {
             for x in 0..engine.fd.width {
                 renderer.set_draw_color(match (engine.fd.occupies((x, y)), engine.bk.occupies((x, y)), ghost.occupies((x, y))) {
                     (true, true,  _)      => Color::RGB(255, 0, 0),
-                    (true, false, _)      => COLORMAP[engine.fd.get((x, y)) as usize],
-                    (false, true, _)      => COLORMAP[engine.bk.id as usize],
+                    (true, false, _)      => {
+                        match engine.fd.appearance((x, y)) {
+                            Appearance::Colour(id) => colour(&engine, id),
+                            Appearance::Plain => Color::RGB(150, 150, 150),
+                            _ => Color::RGB(0, 0, 0)
+                        }
+                    },
+                    (false, true, _)      => colour(&engine, engine.bk.id),
                     (false, false, true)  => {
-                        let (r, g, b) = COLORMAP[engine.bk.id as usize].rgb();
+                        let (r, g, b) = colour(&engine, engine.bk.id).rgb();
                         Color::RGBA(20 + r / 7, 20 + g / 7, 20 + b / 7, 50)
                     },
-                    (false, false, false) => Color::RGB(0, 0, 0)
+                    (false, false, false) => {
+                        match engine.pl.iter().find(|p| p.bk.occupies((x, y))) {
+                            Some(p) => colour(&engine, p.bk.id),
+                            None => Color::RGB(0, 0, 0)
+                        }
+                    }
                 });
 
                 let _ = renderer.fill_rect(sq!(LEFT_FIELD_POSITION + 15 * x as u32,
@@ -144,19 +152,20 @@ fn main() {
         let mut yoffset = UPPER_MARGIN2;
 
         // Draw preview pieces
-        for id in engine.rd.preview(3) { //engine.op.preview_count as usize) {
-            renderer.set_draw_color(COLORMAP[id as usize]);
+        for id in engine.next_queue() {
+            renderer.set_draw_color(colour(&engine, id));
             for &(x, y) in engine.bk.rs.data(id, Rotation::R0) {
                 let _ = renderer.fill_rect(sq!(xoffset + 15 * x as u32, yoffset + 15 * y as u32, 15));
             }
             yoffset += 4 * 15 + 15;
         }
 
-        // Draw hold piece
-        if engine.hd.is_some() {
-            renderer.set_draw_color(COLORMAP[engine.hd.unwrap() as usize]);
-            for &(x, y) in engine.bk.rs.data(engine.hd.unwrap(), Rotation::R0) {
-                let _ = renderer.fill_rect(sq!(LEFT_FIELD_POSITION - 15 * 4 - 20 + 15 * x as u32, UPPER_MARGIN2 + 15 * y as u32, 15));
+        // Draw hold pieces
+        for (i, id) in engine.hd.held().into_iter().enumerate() {
+            let yoffset = UPPER_MARGIN2 + (4 * 15 + 15) * i as u32;
+            renderer.set_draw_color(colour(&engine, id));
+            for &(x, y) in engine.bk.rs.data(id, Rotation::R0) {
+                let _ = renderer.fill_rect(sq!(LEFT_FIELD_POSITION - 15 * 4 - 20 + 15 * x as u32, yoffset + 15 * y as u32, 15));
             }
         }
 
@@ -0,0 +1,80 @@
+//! Analyse a randomizer and write a report.
+//!
+//! ```text
+//! randomizer_stats <randomizer> [--piece-set <name>] [--seed <n>]
+//!                  [--games <n>] [--length <n>]
+//!                  [--format json|csv|drought-csv] [--output <file>]
+//! ```
+//!
+//! The report is written to standard output unless an output file is given.
+
+extern crate tetrs;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::process;
+
+use tetrs::piece_set;
+use tetrs::randomizer::analysis::{self, AnalysisOptions};
+
+const USAGE: &str = "usage: randomizer_stats <randomizer> [--piece-set <name>] [--seed <n>]
+                        [--games <n>] [--length <n>]
+                        [--format json|csv|drought-csv] [--output <file>]";
+
+/// Parse the value of a numeric option.
+fn number<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", option, value))
+}
+
+fn run() -> Result<(), String> {
+    let mut args = env::args().skip(1);
+    let name = args.next().ok_or(USAGE)?;
+
+    let mut options = AnalysisOptions { ..Default::default() };
+    let mut piece_set = "tetromino".to_string();
+    let mut format = "json".to_string();
+    let mut output = None;
+
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {}", option))?;
+
+        match option.as_str() {
+            "--piece-set" => piece_set = value,
+            "--seed" => options.seed = number(&option, &value)?,
+            "--games" => options.games = number(&option, &value)?,
+            "--length" => options.length = number(&option, &value)?,
+            "--format" => format = value,
+            "--output" => output = Some(value),
+            _ => return Err(format!("unknown option: {}\n{}", option, USAGE))
+        }
+    }
+
+    let pieces = piece_set::new(&piece_set)?.pieces;
+    let report = analysis::analyse(&name, &pieces, &options)?;
+
+    let report = match format.as_str() {
+        "json" => report.to_json() + "\n",
+        "csv" => report.to_csv(),
+        "drought-csv" => report.drought_csv(),
+        _ => return Err(format!("unknown format: {}", format))
+    };
+
+    match output {
+        Some(filename) => {
+            File::create(&filename).and_then(|mut f| f.write_all(report.as_bytes()))
+                                   .map_err(|e| format!("{}: {}", filename, e))
+        },
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -18,28 +18,69 @@
 //!             });
 //! ```
 
-use field::Field;
-use rotation_system::{self, RotationSystem};
+use field::{Field, SpawnRegion};
+use std::sync::Arc;
 
-use std::mem;
+use rotation_system::{self, RotationSystem, Centre};
+
+use std::{cmp, fmt, mem};
+use piece_set;
+use serde::{de, Serialize, Serializer, Deserialize, Deserializer};
 
 /// The identifier for a particular `Block`.
-#[repr(usize)]
-#[derive(Hash, Clone, Debug, Copy, PartialEq, Eq)]
-#[allow(missing_docs)]
-pub enum Id {
-    I, T, L, J, S, Z, O, None
+///
+/// An `Id` is an index into the global piece table (see `piece_set`). The
+/// seven tetrominoes are available as associated constants.
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Id(usize);
+
+#[allow(non_upper_case_globals, missing_docs)]
+impl Id {
+    pub const I: Id = Id(0);
+    pub const T: Id = Id(1);
+    pub const L: Id = Id(2);
+    pub const J: Id = Id(3);
+    pub const S: Id = Id(4);
+    pub const Z: Id = Id(5);
+    pub const O: Id = Id(6);
+
+    /// The empty piece, used for unoccupied field cells.
+    pub const None: Id = Id(!0);
 }
 
 impl From<usize> for Id {
-	fn from(t: usize) -> Self {
-		assert!(t < 7);
-		unsafe { mem::transmute(t) }
-	}
+    fn from(t: usize) -> Self {
+        Id(t)
+    }
+}
+
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// An `Id` is serialized by name, since the index of a registered piece can
+// differ between runs.
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Id, D::Error> {
+        let name = String::deserialize(deserializer)?;
+
+        match name.as_str() {
+            "None" => Ok(Id::None),
+            _ => Id::from_name(&name).ok_or_else(|| de::Error::custom(format!("unknown piece: {}", name)))
+        }
+    }
 }
 
 impl Id {
-	/// Returns all `Id` variants known.
+    /// Returns the seven tetromino `Id`s.
     ///
     /// This does not return the `None` variant.
     pub fn variants() -> &'static [Id] {
@@ -49,6 +90,35 @@ impl Id {
 
         VARIANTS
     }
+
+    /// Returns the `Id` with the specified name (e.g. `"T"`).
+    ///
+    /// This does not return the `None` variant.
+    pub fn from_name(name: &str) -> Option<Id> {
+        piece_set::find(name)
+    }
+
+    /// Returns the index of this `Id` in the piece table.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    /// Returns the name of this piece.
+    pub fn name(&self) -> &'static str {
+        piece_set::get(*self).map_or("None", |piece| piece.name)
+    }
+
+    /// Returns the offsets of this piece in its spawn orientation.
+    ///
+    /// The `None` variant has no offsets.
+    pub fn shape(&self) -> &'static [(usize, usize)] {
+        piece_set::get(*self).map_or(&[], |piece| piece.shape)
+    }
+
+    /// Returns the default `(r, g, b)` colour of this piece.
+    pub fn colour(&self) -> (u8, u8, u8) {
+        piece_set::get(*self).map_or((0, 0, 0), |piece| piece.colour)
+    }
 }
 
 /// Represents all rotation statuses a block can be. This is used both as
@@ -70,7 +140,7 @@ impl Id {
 ///   ---------      ---------
 /// ```
 #[repr(usize)]
-#[derive(Hash, Clone, Debug, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Hash, Clone, Debug, Copy, PartialEq)]
 #[allow(missing_docs)]
 pub enum Rotation {
     R0, R90, R180, R270
@@ -158,6 +228,10 @@ impl Direction {
 /// Further, a block could have many different internal representations which
 /// appear equal, by adjusting the `(x, y)` coordinates and `data` in
 /// conjunction.
+///
+/// A block can be scaled (e.g. for TGM "Big" mode), in which case every mino
+/// of the block covers a `scale` x `scale` square of cells. The same rotation
+/// system data is used, with all offsets and movements multiplied by `scale`.
 #[derive(Clone)]
 pub struct Block {
     /// X-coordinate of the piece
@@ -173,7 +247,10 @@ pub struct Block {
     pub r: Rotation,
 
     /// Rotation system used to calculate block offsets.
-    pub rs: &'static RotationSystem
+    pub rs: Arc<RotationSystem>,
+
+    /// The width and height of each mino in cells.
+    pub scale: i32
 }
 
 /// Optional values which can be set when initializing a `Block`.
@@ -185,12 +262,16 @@ pub struct Block {
 ///     x: None,
 ///     y: None,
 ///     rotation: Rotation::R0,
-///     rotation_system: rotation_system::SRS
+///     rotation_system: rotation_system::SRS,
+///     scale: None
 /// }
 /// ```
 ///
 /// If an `x` or `y` value is `Some(..)` then it will override the fields spawn
-/// position, which is usually used on `Block` construction.
+/// position, which is usually used on `Block` construction. The spawn rule of
+/// the rotation system is not applied, see `Block::spawn` for this.
+///
+/// If `scale` is `None` then the scale of the field is used.
 ///
 /// ## Examples
 ///
@@ -211,7 +292,9 @@ pub struct BlockOptions {
 
     pub rotation: Rotation,
 
-    pub rotation_system: &'static RotationSystem
+    pub rotation_system: Arc<RotationSystem>,
+
+    pub scale: Option<i32>
 }
 
 impl Default for BlockOptions {
@@ -220,7 +303,8 @@ impl Default for BlockOptions {
             x: None,
             y: None,
             rotation: Rotation::R0,
-            rotation_system: rotation_system::new("srs").unwrap()
+            rotation_system: rotation_system::new("srs").unwrap(),
+            scale: None
         }
     }
 }
@@ -233,6 +317,9 @@ impl Block {
 
     /// Construct a `Block` object with specific values.
     ///
+    /// The block is placed exactly where the options specify. Use
+    /// `Block::spawn` for a block placed by the rotation system.
+    ///
     /// ## Examples
     /// ```
     /// use tetrs::import::*;
@@ -245,25 +332,179 @@ impl Block {
     /// ```
     pub fn with_options(id: Id, field: &Field, options: BlockOptions) -> Block {
         Block {
-            id: id,
-            x: if options.x.is_none() { field.spawn.0 } else { options.x.unwrap() },
-            y: if options.y.is_none() { field.spawn.1 } else { options.y.unwrap() },
+            id,
+            x: options.x.unwrap_or(field.spawn.0),
+            y: options.y.unwrap_or(field.spawn.1),
             r: options.rotation,
-            rs: options.rotation_system
+            rs: options.rotation_system,
+            scale: options.scale.unwrap_or(field.scale)
         }
     }
 
+    /// Construct a `Block` following the `SpawnRule` of a rotation system.
+    ///
+    /// The rule is applied relative to `spawn`. If `None`, it is applied
+    /// relative to the spawn region of the field, or the field spawn if the
+    /// field has no region.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::rotation_system::Custom;
+    /// use std::sync::Arc;
+    ///
+    /// let rs = Custom::from_json(r#"{
+    ///     "pieces": {
+    ///         "I2": {
+    ///             "rotations": [[[0, 0], [1, 0]], [[0, 0], [0, 1]],
+    ///                           [[0, 0], [1, 0]], [[0, 0], [0, 1]]],
+    ///             "spawn_rotation": "R90"
+    ///         }
+    ///     },
+    ///     "spawn_row": 0,
+    ///     "spawn_drop": true
+    /// }"#).unwrap();
+    ///
+    /// let field = Field::new();
+    /// let id = block::Id::from_name("I2").unwrap();
+    /// let block = Block::spawn(id, &field, None, Arc::new(rs));
+    ///
+    /// assert_eq!(block.r, Rotation::R90);
+    /// assert_eq!((block.x, block.y), (field.spawn.0, field.hidden as i32 + 1));
+    /// ```
+    pub fn spawn(id: Id, field: &Field, spawn: Option<(i32, i32)>,
+                 rotation_system: Arc<RotationSystem>) -> Block {
+        let rule = rotation_system.spawn_rule(id);
+
+        // A spawn point covers the centre column of an odd-width field, or
+        // the left of the two centre columns of an even-width field, and its
+        // spawn rows are relative to the first visible row
+        let region = if spawn.is_none() { field.spawn_region } else { None };
+        let (area, origin) = match region {
+            Some(region) => (region, region.y),
+            None => {
+                let (x, y) = spawn.unwrap_or(field.spawn);
+                let area = SpawnRegion { x, y, width: 2 - field.width % 2, height: 1 };
+                (area, i32!(field.hidden))
+            }
+        };
+
+        let (x, y) = Block::spawn_position(id, field, area, origin, &rotation_system,
+                                           rule.rotation, field.scale);
+
+        let mut block = Block::with_options(id, field, BlockOptions {
+            x: Some(x), y: Some(y), rotation: rule.rotation, rotation_system, scale: None
+        });
+
+        // Move the block down through a spawn region until it fits
+        if let Some(region) = region {
+            let lowest = block.offsets().map(|(_, dy)| block.y + dy).max().unwrap_or(block.y);
+            let bottom = region.y + i32!(region.height) - 1;
+
+            let fits = |&dy: &i32| !block.collides_at_offset(field, (0, dy));
+            if let Some(dy) = (0..=bottom - lowest).find(fits) {
+                block.y += dy;
+            }
+        }
+
+        if rule.drop {
+            block.shift(field, Direction::Down);
+        }
+
+        block
+    }
+
+    /// Return the position a block spawns at, before any drop.
+    ///
+    /// Rule rows are relative to the row `origin`.
+    fn spawn_position(id: Id, field: &Field, area: SpawnRegion, origin: i32,
+                      rs: &Arc<RotationSystem>, rotation: Rotation, scale: i32) -> (i32, i32) {
+        let rule = rs.spawn_rule(id);
+
+        let x = match rule.centre {
+            Some(centre) => {
+                // Twice the centre line of the spawn area
+                let middle = 2 * area.x + i32!(area.width);
+
+                let (left, right) = (i32!(rs.min(id, rotation).0), i32!(rs.max(id, rotation).0));
+                let extra = middle - (right - left + 1) * scale;
+                let x = match centre {
+                    Centre::Left => extra.div_euclid(2),
+                    Centre::Right => (extra + 1).div_euclid(2)
+                };
+
+                x - left * scale
+            },
+            None => area.x
+        };
+
+        let y = rule.row.map_or(area.y, |row| origin + row);
+        let mut spawn = (x + rule.offset.0, y + rule.offset.1);
+
+        // Scaled blocks must lie on a grid aligned with the left wall and the
+        // floor so they can fill complete rows. They must also fit within the
+        // field horizontally.
+        if scale > 1 {
+            let width = (i32!(rs.max(id, rotation).0) + 1) * scale;
+            let x = cmp::min(spawn.0, i32!(field.width) - width);
+
+            spawn = (x - x % scale, spawn.1 + (i32!(field.height) - spawn.1) % scale);
+        }
+
+        spawn
+    }
+
+    /// Return the offsets of each cell this block occupies, relative to its
+    /// `(x, y)` position.
+    ///
+    /// Each mino covers a `scale` x `scale` square of cells.
+    pub fn offsets<'a>(&'a self) -> impl Iterator<Item = (i32, i32)> + 'a {
+        let scale = self.scale;
+
+        iproduct!(self.rs.data(self.id, self.r).iter(), 0..scale, 0..scale)
+            .map(move |(&(x, y), i, j)| (i32!(x) * scale + i, i32!(y) * scale + j))
+    }
+
+    /// Return the absolute `(x, y)` locations this block occupies.
+    ///
+    /// The locations are sorted, so two blocks which appear identical on a
+    /// field will return the same values, regardless of their internal
+    /// representation.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let field = Field::new();
+    /// let block = Block::with_options(block::Id::O, &field, BlockOptions {
+    ///                 x: Some(0), y: Some(0),
+    ///                 ..Default::default()
+    ///             });
+    ///
+    /// assert_eq!(block.cells(), vec![(1, 0), (1, 1), (2, 0), (2, 1)]);
+    /// ```
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        let mut cells = self.offsets()
+                            .map(|(x, y)| (self.x + x, self.y + y))
+                            .collect::<Vec<_>>();
+        cells.sort();
+        cells
+    }
+
     /// Return whether the block collides with `field` at the specified offset.
+    ///
+    /// The offset is in cells, and is not multiplied by `scale`.
     pub fn collides_at_offset(&self, field: &Field, (xo, yo): (i32, i32)) -> bool {
-        self.rs.data(self.id, self.r).iter()
-                  .map(|&(dx, dy)| {
-                      (self.x + i32!(dx) + xo, self.y + i32!(dy) + yo)
-                  })
-                  .any(|(x, y)| {
-                      x < 0 || usize!(x) >= field.width ||
-                      y < 0 || usize!(y) >= field.height ||
-                      field.get((usize!(x), usize!(y))) != Id::None
-                  })
+        let (x, y) = (self.x + xo, self.y + yo);
+
+        // This is used heavily when searching, so avoid scaling when possible
+        if self.scale == 1 {
+            self.rs.data(self.id, self.r).iter()
+                .any(|&(dx, dy)| field.collides((x + i32!(dx), y + i32!(dy))))
+        }
+        else {
+            self.offsets().any(|(dx, dy)| field.collides((x + dx, y + dy)))
+        }
     }
 
     /// Return whether the current `Block` collides with `field` at its current
@@ -289,6 +530,8 @@ impl Block {
 
     /// Shift the block one step in the specified direction.
     ///
+    /// A step is `scale` cells.
+    ///
     /// ## Examples
     /// ```
     /// use tetrs::import::*;
@@ -305,7 +548,7 @@ impl Block {
             _ => panic!("Found invalid direction: {:?}", direction)
         };
 
-        self.shift_raw(&field, (x, y))
+        self.shift_raw(field, (x * self.scale, y * self.scale))
     }
 
     /// Repeatedly shift a block as far as we can until a collision occurs.
@@ -329,6 +572,9 @@ impl Block {
     /// This is useful for calculating wallkicks. See the `rotate_with_wallkick`
     /// function in the `utility` module for an easier function.
     ///
+    /// The offset is multiplied by `scale`, so wallkick tables can be used
+    /// unchanged for scaled blocks.
+    ///
     /// ```
     /// use tetrs::import::*;
     ///
@@ -350,7 +596,7 @@ impl Block {
 
         self.r = new_rotation;
 
-        if self.shift_raw(&field, (x, y)) {
+        if self.shift_raw(field, (x * self.scale, y * self.scale)) {
             true
         }
         else {
@@ -366,8 +612,8 @@ impl Block {
 
     /// Check if the block occupies a particular `(x, y)` absolute location.
     pub fn occupies(&self, (a, b): (usize, usize)) -> bool {
-        self.rs.data(self.id, self.r).iter()
-            .map(|&(x, y)| (self.x + i32!(x), self.y + i32!(y)))
+        self.offsets()
+            .map(|(x, y)| (self.x + x, self.y + y))
             .any(|(x, y)| a == usize!(x) && b == usize!(y))
     }
 
@@ -392,7 +638,7 @@ impl Block {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use field::Field;
+    use field::{Field, FieldOptions, SpawnRegion};
 
     #[test]
     fn test_shift() {
@@ -424,4 +670,66 @@ mod tests {
         block.rotate(&field, Rotation::R270);
         assert_eq!(block.r, Rotation::R90);
     }
+
+    #[test]
+    fn test_scale() {
+        let field = Field::with_options(FieldOptions {
+            scale: 2, ..Default::default()
+        });
+
+        let mut block = Block::spawn(Id::I, &field, None, rotation_system::new("srs").unwrap());
+        assert_eq!(block.scale, 2);
+        assert_eq!(block.cells().len(), 16);
+        assert!(!block.collides(&field));
+
+        // Blocks move in steps of two cells
+        let x = block.x;
+        block.shift(&field, Direction::Right);
+        assert_eq!(x + 2, block.x);
+
+        block.shift_extend(&field, Direction::Left);
+        assert_eq!(0, block.x);
+
+        // The block rests on the floor
+        block.shift_extend(&field, Direction::Down);
+        assert_eq!(block.cells().iter().map(|&(_, y)| y).max(), Some(24));
+
+        // Rotating uses the same data, scaled
+        assert!(!block.rotate(&field, Rotation::R90));
+        block.shift_raw(&field, (0, -6));
+        assert!(block.rotate(&field, Rotation::R90));
+
+        let mut columns = block.cells().iter().map(|&(x, _)| x).collect::<Vec<_>>();
+        columns.dedup();
+        assert_eq!(columns, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_spawn_region() {
+        let mut field = Field::with_options(FieldOptions {
+            spawn_region: Some(SpawnRegion { x: 6, y: 4, width: 4, height: 3 }),
+            ..Default::default()
+        });
+        let rs = rotation_system::new("tengen").unwrap();
+
+        // The block is centred on the columns of the region, at its top
+        let block = Block::spawn(Id::I, &field, None, rs.clone());
+        assert_eq!(block.cells(), vec![(6, 4), (7, 4), (8, 4), (9, 4)]);
+
+        // A block which does not fit is moved down through the region
+        field.set((7, 4), Id::O);
+        let block = Block::spawn(Id::I, &field, None, rs.clone());
+        assert_eq!(block.cells(), vec![(6, 5), (7, 5), (8, 5), (9, 5)]);
+
+        // It is left at the top if it fits nowhere in the region
+        field.set((7, 5), Id::O);
+        field.set((7, 6), Id::O);
+        let block = Block::spawn(Id::I, &field, None, rs.clone());
+        assert_eq!(block.cells(), vec![(6, 4), (7, 4), (8, 4), (9, 4)]);
+        assert!(block.collides(&field));
+
+        // An explicit spawn point ignores the region
+        let block = Block::spawn(Id::I, &field, Some(field.spawn), rs.clone());
+        assert_eq!(block.cells(), Block::spawn(Id::I, &Field::new(), None, rs).cells());
+    }
 }
@@ -0,0 +1,536 @@
+//! A reference bot which plays the engine.
+//!
+//! The bot evaluates every reachable placement of the current piece using a
+//! weighted heuristic, and searches further placements using the randomizer
+//! preview and hold piece.
+//!
+//! A `Bot` can either drive an `Engine` through its `Controller` on a per-tick
+//! basis, or place pieces directly using `Engine::place` for fast headless
+//! games.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::bot::Bot;
+//!
+//! let mut engine = Engine::new(EngineOptions { ..Default::default() });
+//! let mut bot = Bot::new();
+//!
+//! // Play a few pieces as fast as possible
+//! for _ in 0..5 {
+//!     bot.play(&mut engine).unwrap();
+//! }
+//!
+//! // Alternatively, drive the controller each tick
+//! for _ in 0..100 {
+//!     bot.drive(&mut engine);
+//!     engine.update();
+//! }
+//! ```
+
+use std::cmp;
+use std::collections::VecDeque;
+
+use block::{self, Block};
+use field::Field;
+use engine::{Engine, Placement};
+use hold::HoldMode;
+use controller::Action;
+use movegen::{self, Input, Move};
+use wallkick::Wallkick;
+use clear_rule::ClearRule;
+
+/// The weights applied to each heuristic of a field.
+///
+/// Positive values are favourable, negative values are unfavourable. The
+/// default line, height, hole and bumpiness values are the genetically tuned
+/// weights from Yiyuan Lee's "Tetris AI", with small hand-tuned weights added
+/// for wells and T-slots.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Weights {
+    /// Number of lines cleared by a placement
+    pub lines: f64,
+
+    /// Sum of all column heights
+    pub aggregate_height: f64,
+
+    /// Number of empty cells with a filled cell above them
+    pub holes: f64,
+
+    /// Sum of the height difference between adjacent columns
+    pub bumpiness: f64,
+
+    /// Sum of the depth of all wells
+    pub wells: f64,
+
+    /// Number of T-slots ready to be filled
+    pub t_slots: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            lines: 0.76, aggregate_height: -0.51, holes: -0.36,
+            bumpiness: -0.18, wells: -0.1, t_slots: 0.2
+        }
+    }
+}
+
+/// Computes a score for a field using a set of `Weights`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Evaluator {
+    /// The weights of each heuristic
+    pub weights: Weights
+}
+
+impl Evaluator {
+    /// Construct a new `Evaluator` with the specified weights.
+    pub fn new(weights: Weights) -> Evaluator {
+        Evaluator { weights }
+    }
+
+    /// Return the score of `field`, where a higher score is better.
+    ///
+    /// `lines` is the number of lines cleared to reach this field.
+    pub fn evaluate(&self, field: &Field, lines: usize) -> f64 {
+        let metrics = field.metrics();
+
+        let w = &self.weights;
+        w.lines * lines as f64 +
+            w.aggregate_height * metrics.aggregate_height as f64 +
+            w.holes * metrics.holes as f64 +
+            w.bumpiness * metrics.bumpiness as f64 +
+            w.wells * metrics.wells.iter().sum::<usize>() as f64 +
+            w.t_slots * metrics.t_slots.len() as f64
+    }
+
+    /// Return the score of the best placement of `block` on `field`, where a
+    /// higher score is better.
+    ///
+    /// Rows are removed with `rule` and then fall using the gravity of the
+    /// field, as they would in the game. If the block cannot be placed the
+    /// score is negative infinity.
+    ///
+    /// This can be used as an `Engine::score_hook`.
+    pub fn best_score(&self, field: &Field, block: &Block, wk: &Wallkick, rule: &ClearRule) -> f64 {
+        movegen::generate(field, block, wk).into_iter().map(|m| {
+            let mut field = field.clone();
+            field.freeze(m.block);
+            let lines = field.clear_with(|field| rule.find(field)).lines;
+            self.evaluate(&field, lines)
+        }).fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+/// Optional values which can be set when initializing a `Bot`.
+///
+/// The default values are:
+///
+/// ```text
+/// BotOptions {
+///     evaluator: Evaluator::default(),
+///     lookahead: 1,
+///     beam_width: 8
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BotOptions {
+    /// The evaluator used to score fields
+    pub evaluator: Evaluator,
+
+    /// How many preview pieces are searched
+    pub lookahead: usize,
+
+    /// How many candidates are kept after each searched piece
+    pub beam_width: usize,
+}
+
+impl Default for BotOptions {
+    fn default() -> BotOptions {
+        BotOptions {
+            evaluator: Evaluator::default(),
+            lookahead: 1,
+            beam_width: 8
+        }
+    }
+}
+
+/// A single controller step when driving an engine.
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    /// Press the action for a single tick
+    Press(Action),
+
+    /// Release all actions for a single tick
+    Release,
+
+    /// Hold a movement action until the block can no longer move
+    Extend(Action)
+}
+
+/// A placement, the inputs which reach it and the cells it fills.
+type Choice = (Placement, Vec<Input>, Vec<(i32, i32)>);
+
+/// A candidate field during the search.
+#[derive(Clone)]
+struct Node {
+    /// The field after all placements
+    field: Field,
+
+    /// Index of the next unused piece in the queue
+    next: usize,
+
+    /// The current hold piece
+    hold: Option<block::Id>,
+
+    /// The first placement made
+    first: Option<Choice>,
+
+    /// Total lines cleared along this path
+    lines: usize,
+
+    /// Evaluated score of this node
+    score: f64
+}
+
+/// A bot which plays an `Engine`.
+pub struct Bot {
+    /// Options used when searching
+    pub op: BotOptions,
+
+    /// Remaining controller steps for the current piece
+    plan: VecDeque<Step>,
+
+    /// The placement being driven towards and the cells it fills
+    target: Option<(Placement, Vec<(i32, i32)>)>
+}
+
+impl Default for Bot {
+    fn default() -> Bot {
+        Bot::new()
+    }
+}
+
+impl Bot {
+    /// Construct a new `Bot` with default options.
+    pub fn new() -> Bot {
+        Bot::with_options(BotOptions { ..Default::default() })
+    }
+
+    /// Construct a new `Bot` with the specified options.
+    pub fn with_options(options: BotOptions) -> Bot {
+        Bot { op: options, plan: VecDeque::new(), target: None }
+    }
+
+    /// Return the best placement for the active piece of `engine`, and the
+    /// inputs required to reach it after any hold.
+    ///
+    /// `None` is returned if no placement exists.
+    pub fn think(&self, engine: &mut Engine) -> Option<(Placement, Vec<Input>)> {
+        self.search(engine).map(|(placement, inputs, _)| (placement, inputs))
+    }
+
+    /// Return the best placement for the active piece of `engine`, the
+    /// inputs required to reach it and the cells it fills.
+    ///
+    /// The active piece is searched from its current position and every
+    /// following piece from its spawn.
+    fn search(&self, engine: &mut Engine) -> Option<Choice> {
+        let mut queue = vec![engine.bk.id];
+        queue.extend(engine.hd.queued());
+        let lookahead = cmp::min(self.op.lookahead, engine.rd.max_preview());
+        queue.extend(engine.rd.preview(lookahead).into_iter()
+                           .take_while(|&id| id != block::Id::None));
+
+        let root = Node {
+            field: engine.fd.clone(),
+            next: 0,
+            hold: engine.hd.next(),
+            first: None,
+            lines: 0,
+            score: 0f64
+        };
+
+        let mut beam = vec![root];
+        // The search only models swapping with a single held piece
+        let swap = engine.hd.slots() == 1 && engine.hd.op.mode != HoldMode::Preview;
+        let mut can_hold = swap && engine.can_hold();
+
+        for _ in 0..cmp::min(self.op.lookahead + 1, queue.len()) {
+            let mut children = Vec::new();
+
+            for node in &beam {
+                self.expand(engine, node, &queue, can_hold, &mut children);
+            }
+
+            if children.is_empty() {
+                break;
+            }
+
+            children.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+            children.truncate(self.op.beam_width);
+            beam = children;
+
+            // Hold is always available again for subsequent pieces
+            can_hold = swap;
+        }
+
+        beam.into_iter().next().and_then(|node| node.first)
+    }
+
+    /// Expand a node with every placement of its next piece.
+    fn expand(&self, engine: &Engine, node: &Node, queue: &[block::Id], can_hold: bool,
+              children: &mut Vec<Node>) {
+        if node.next >= queue.len() {
+            return;
+        }
+
+        // Each option is the piece to place, the resulting hold piece, the
+        // index of the next piece and whether a hold was used.
+        let mut options = vec![(queue[node.next], node.hold, node.next + 1, false)];
+
+        if can_hold {
+            match node.hold {
+                Some(id) => {
+                    options.push((id, Some(queue[node.next]), node.next + 1, true));
+                },
+                None if node.next + 1 < queue.len() => {
+                    options.push((queue[node.next + 1], Some(queue[node.next]), node.next + 2, true));
+                },
+                None => ()
+            }
+        }
+
+        for (id, hold, next, held) in options {
+            // Pieces are spawned exactly as the engine would spawn them
+            let block = if node.first.is_none() && !held {
+                engine.bk.clone()
+            }
+            else {
+                engine.spawn_on(id, &node.field)
+            };
+
+            let moves = movegen::generate(&node.field, &block, &*engine.wk);
+
+            for m in moves.iter().filter(|m| Bot::placeable(m, &moves)) {
+                let mut field = node.field.clone();
+                field.freeze(m.block.clone());
+                let lines = node.lines + field.clear_lines();
+
+                let first = node.first.clone().or_else(|| {
+                    Some((Placement { x: m.block.x, r: m.block.r, hold: held, spin: m.spin },
+                          m.inputs.clone(), m.block.cells()))
+                });
+
+                children.push(Node {
+                    score: self.op.evaluator.evaluate(&field, lines),
+                    field, next, hold, first, lines
+                });
+            }
+        }
+    }
+
+    /// Returns true if `Engine::place` would place a block at the position
+    /// of `m` when given its placement.
+    ///
+    /// A `Placement` does not specify a row, so when a shape can rest at
+    /// multiple heights in the same columns the engine uses the highest, or
+    /// the lowest if the placement is a spin.
+    fn placeable(m: &Move, moves: &[Move]) -> bool {
+        let shape = m.shape();
+
+        !moves.iter().any(|o| {
+            o.spin == m.spin && o.shape() == shape &&
+                if m.spin { o.top() > m.top() } else { o.top() < m.top() }
+        })
+    }
+
+    /// Place the active piece of `engine` at the best found position.
+    ///
+    /// This uses `Engine::place` and so advances the game until the next
+    /// piece is active.
+    pub fn play(&mut self, engine: &mut Engine) -> Result<(), String> {
+        engine.advance()?;
+
+        match self.think(engine) {
+            Some((placement, _)) => engine.place(placement),
+            None => Err("no placement found".to_string())
+        }
+    }
+
+    /// Set the controller state of `engine` for the next update.
+    ///
+    /// This should be called once before every `Engine::update`. A new
+    /// placement is searched for whenever a new piece becomes active. The
+    /// piece may drift from the planned path, such as under gravity, so the
+    /// remaining inputs are searched again from the active block before each
+    /// new input. If the placement can no longer be reached, a new one is
+    /// searched for from the active block.
+    pub fn drive(&mut self, engine: &mut Engine) {
+        if !engine.piece_active() {
+            self.plan.clear();
+            self.target = None;
+            engine.co.deactivate_all();
+            return;
+        }
+
+        if self.between_inputs(engine) {
+            // Continue towards the current target if it can still be reached
+            let path = self.target.as_ref().and_then(|&(placement, _)| {
+                engine.find_path(Placement { hold: false, ..placement })
+            });
+
+            match path {
+                Some(m) => {
+                    let placement = Placement { hold: false, ..self.target.as_ref().unwrap().0 };
+                    self.plan = Bot::steps(placement, &m.inputs);
+                },
+                None => self.target = None
+            }
+        }
+
+        if self.target.is_none() {
+            match self.search(engine) {
+                Some((placement, inputs, cells)) => {
+                    self.plan = Bot::steps(placement, &inputs);
+                    self.target = Some((placement, cells));
+                },
+                None => {
+                    engine.co.deactivate_all();
+                    return;
+                }
+            }
+        }
+
+        let (bits, done) = match self.plan.front() {
+            Some(&Step::Press(action)) => (action.bit(), true),
+            Some(&Step::Release) | None => (0, true),
+            Some(&Step::Extend(action)) => {
+                let offset = match action {
+                    Action::MoveLeft => (-1, 0),
+                    Action::MoveRight => (1, 0),
+                    _ => (0, 1)
+                };
+
+                if engine.bk.collides_at_offset(&engine.fd, offset) {
+                    (0, true)
+                }
+                else {
+                    (action.bit(), false)
+                }
+            }
+        };
+
+        engine.co.activate_bits(bits);
+
+        if done {
+            self.plan.pop_front();
+        }
+    }
+
+    /// Returns true if a plan is being followed, no input is in progress and
+    /// the next input is not a hold.
+    fn between_inputs(&self, engine: &Engine) -> bool {
+        match self.plan.front() {
+            Some(&Step::Press(Action::Hold)) | Some(&Step::Release) | None => false,
+            Some(_) => engine.co.active.iter().all(|&active| !active)
+        }
+    }
+
+    /// Convert a placement and its inputs into controller steps.
+    fn steps(placement: Placement, inputs: &[Input]) -> VecDeque<Step> {
+        let mut steps = VecDeque::new();
+
+        if placement.hold {
+            steps.push_back(Step::Press(Action::Hold));
+            steps.push_back(Step::Release);
+        }
+
+        for input in inputs {
+            let action = match *input {
+                Input::Left => Action::MoveLeft,
+                Input::Right => Action::MoveRight,
+                Input::RotateLeft => Action::RotateLeft,
+                Input::RotateRight => Action::RotateRight,
+                Input::SoftDrop => {
+                    steps.push_back(Step::Extend(Action::MoveDown));
+                    continue;
+                },
+                Input::DasLeft => {
+                    steps.push_back(Step::Extend(Action::MoveLeft));
+                    continue;
+                },
+                Input::DasRight => {
+                    steps.push_back(Step::Extend(Action::MoveRight));
+                    continue;
+                }
+            };
+
+            steps.push_back(Step::Press(action));
+            steps.push_back(Step::Release);
+        }
+
+        steps.push_back(Step::Press(Action::HardDrop));
+        steps.push_back(Step::Release);
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use import::*;
+
+    #[test]
+    fn test_evaluate() {
+        let evaluator = Evaluator::default();
+
+        let flat = Field::new();
+        let mut holes = Field::new();
+        holes.set((4, 23), block::Id::I);
+
+        assert!(evaluator.evaluate(&flat, 0) > evaluator.evaluate(&holes, 0));
+    }
+
+    #[test]
+    fn test_long_game() {
+        let mut engine = Engine::new(EngineOptions { ..Default::default() });
+        let mut bot = Bot::new();
+
+        for _ in 0..30 {
+            bot.play(&mut engine).unwrap();
+        }
+
+        assert!(engine.running);
+    }
+
+    #[test]
+    fn test_drive() {
+        // Pieces fall while the bot is moving them
+        let mut engine = Engine::new(EngineOptions {
+            engine_settings: EngineSettings { gravity: 0.3, ..Default::default() },
+            randomizer_sequence: "TLJSZOI".to_string(),
+            sequence_exhausted: randomizer::Exhausted::Repeat,
+            ..Default::default()
+        });
+        let mut bot = Bot::new();
+        let mut drops = 0;
+
+        for _ in 0..300 {
+            bot.drive(&mut engine);
+
+            // Every piece is dropped from above its planned cells
+            if engine.co.active(Action::HardDrop) {
+                let cells = &bot.target.as_ref().unwrap().1;
+                assert_eq!(&engine.bk.ghost(&engine.fd).cells(), cells);
+                drops += 1;
+            }
+
+            engine.update();
+        }
+
+        assert!(engine.running);
+        assert!(drops >= 5, "{}", drops);
+    }
+}
@@ -0,0 +1,26 @@
+//! Implements the colour match clear rule.
+//!
+//! A filled row is only removed if every cell within it has the same `Id`.
+//! Masked cells are ignored.
+
+use block::Id;
+use field::{Field, Mask};
+use clear_rule::{ClearRule, Removal};
+
+gen_clear_rule!(Colour);
+
+impl ClearRule for Colour {
+    fn find(&self, field: &Field) -> Removal {
+        Removal {
+            rows: (0..field.height).filter(|&y| {
+                      let mut ids = (0..field.width).filter(|&x| field.mask((x, y)) == Mask::Open)
+                                                    .map(|x| field.get((x, y)));
+
+                      let first = ids.next().unwrap_or(Id::None);
+                      field.is_full(y) && ids.all(|id| id == first)
+                  })
+                  .collect(),
+            cells: Vec::new()
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Implements the four line clear rule.
+//!
+//! Filled rows are only removed when at least four are present at once.
+//! This is typically used for puzzle modes which only reward tetrises.
+
+use field::Field;
+use clear_rule::{ClearRule, Removal, Lines};
+
+gen_clear_rule!(Four);
+
+impl ClearRule for Four {
+    fn find(&self, field: &Field) -> Removal {
+        let removal = Lines::new().find(field);
+
+        if removal.rows.len() >= 4 {
+            removal
+        }
+        else {
+            Removal::default()
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Implements the standard line clear rule.
+//!
+//! Every completely filled row is removed.
+
+use field::Field;
+use clear_rule::{ClearRule, Removal};
+
+gen_clear_rule!(Lines);
+
+impl ClearRule for Lines {
+    fn find(&self, field: &Field) -> Removal {
+        Removal {
+            rows: (0..field.height).filter(|&y| field.is_full(y)).collect(),
+            cells: Vec::new()
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! A module for specifying which parts of a field are cleared.
+//!
+//! A clear rule inspects a field and returns the rows and individual cells
+//! which should be removed from it. The `Field` is then responsible for
+//! removing them and applying gravity, so new rules do not require any
+//! changes to `Field` itself.
+//!
+//! User clear rules can be implemented, and only require the `ClearRule`
+//! trait to be implemented.
+
+use field::Field;
+
+/// The rows and cells removed from a field by a single clear.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Removal {
+    /// Rows which are removed entirely
+    pub rows: Vec<usize>,
+
+    /// Individual cells which are removed
+    ///
+    /// These are removed without removing the rest of their row.
+    pub cells: Vec<(usize, usize)>
+}
+
+impl Removal {
+    /// Returns true if nothing is removed.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.cells.is_empty()
+    }
+}
+
+/// The `ClearRule` trait must be implemented by all clear rules.
+///
+/// Like wallkicks, clear rules usually have no state and so static
+/// references are preferred.
+pub trait ClearRule {
+    /// Return the rows and cells of `field` which should be removed.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// for x in 0..field.width {
+    ///     field.set((x, 24), block::Id::I);
+    /// }
+    ///
+    /// let rule = clear_rule::new("lines").unwrap();
+    /// assert_eq!(rule.find(&field).rows, vec![24]);
+    /// ```
+    fn find(&self, field: &Field) -> Removal;
+}
+
+macro_rules! gen_clear_rule {
+    ($crid:ident) => {
+        #[allow(missing_docs)]
+        pub struct $crid;
+
+        static __INSTANCE: $crid = $crid {};
+
+        impl $crid {
+            /// Returns a static instance to this clear rule.
+            pub fn new() -> &'static $crid {
+                &__INSTANCE
+            }
+        }
+    }
+}
+
+pub use self::lines::Lines;
+pub use self::colour::Colour;
+pub use self::four::Four;
+pub use self::square::Square;
+
+mod lines;
+mod colour;
+mod four;
+mod square;
+
+/// Factory function for constructing clear rules from name.
+///
+/// # Names
+///  - `lines`
+///  - `colour`
+///  - `four`
+///  - `square`
+pub fn new(name: &str) -> Result<&'static ClearRule, String> {
+    match name {
+        "lines" => Ok(Lines::new()),
+        "colour" => Ok(Colour::new()),
+        "four" => Ok(Four::new()),
+        "square" => Ok(Square::new()),
+        _ => Err(format!("unknown clear rule: {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use import::*;
+
+    fn field(rows: &[&str]) -> Field {
+        let mut field = Field::new();
+        let offset = field.height - rows.len();
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let id = match c {
+                    'I' => block::Id::I,
+                    'O' => block::Id::O,
+                    'T' => block::Id::T,
+                    _ => block::Id::None
+                };
+
+                field.set((x, offset + y), id);
+            }
+        }
+
+        field
+    }
+
+    #[test]
+    fn test_colour() {
+        let mut field = field(&[
+            "IIIIIIIIIO",
+            "IIIIIIIIII",
+        ]);
+
+        let clear = field.clear_with(|f| Colour::new().find(f));
+        assert_eq!(clear.removed, vec![Removal { rows: vec![24], cells: vec![] }]);
+        assert_eq!(field.get((9, 24)), block::Id::O);
+    }
+
+    #[test]
+    fn test_four() {
+        let mut field = field(&[
+            "IIIIIIIII ",
+            "IIIIIIIII ",
+            "IIIIIIIII ",
+            "IIIIIIIII ",
+        ]);
+
+        assert!(Four::new().find(&field).is_empty());
+
+        for y in 21..25 {
+            field.set((9, y), block::Id::I);
+        }
+
+        assert_eq!(Four::new().find(&field).rows, vec![21, 22, 23, 24]);
+    }
+
+    #[test]
+    fn test_square() {
+        let mut field = field(&[
+            "T         ",
+            "OOO       ",
+            "OOO   TT  ",
+            "IIT   TTI ",
+        ]);
+
+        let clear = field.clear_with(|f| Square::new().find(f));
+        assert_eq!(clear.lines, 0);
+        assert_eq!(clear.removed[0].cells.len(), 10);
+
+        // Cells above the removed squares fall within their column
+        assert_eq!(field.get((0, 23)), block::Id::T);
+        assert_eq!(field.get((2, 24)), block::Id::T);
+        assert!(!field.occupies((6, 24)));
+        assert_eq!(field.rows()[24], 0b1_0000_0111);
+        assert_eq!(field.rows()[23], 0b1);
+    }
+}
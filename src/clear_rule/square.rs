@@ -0,0 +1,29 @@
+//! Implements a square clear rule, similar to that found in Lumines.
+//!
+//! Every 2x2 square of cells which share the same `Id` is removed. Squares
+//! may overlap, and rows are never removed.
+
+use block::Id;
+use field::Field;
+use clear_rule::{ClearRule, Removal};
+
+gen_clear_rule!(Square);
+
+impl ClearRule for Square {
+    fn find(&self, field: &Field) -> Removal {
+        let mut cells = Vec::new();
+
+        for (y, x) in iproduct!(1..field.height, 1..field.width) {
+            let id = field.get((x, y));
+            let square = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)];
+
+            if id != Id::None && square.iter().all(|&p| field.get(p) == id) {
+                cells.extend(square.iter().cloned());
+            }
+        }
+
+        cells.sort();
+        cells.dedup();
+        Removal { rows: Vec::new(), cells }
+    }
+}
@@ -20,7 +20,7 @@ pub type CAarray = [bool; 8];
 
 /// Actions which are understood by the controller.
 #[repr(usize)]
-#[derive(Clone, Copy, Debug, Hash)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[allow(missing_docs)]
 // When adding a new Action you MUST also alter the `History` module to
 // match the new array size!
@@ -36,6 +36,15 @@ impl From<usize> for Action {
 	}
 }
 
+impl Action {
+    /// Returns the bit representing this action in an action bitset.
+    ///
+    /// Bit `n` of a bitset corresponds to `Action::from(n)`.
+    pub fn bit(&self) -> u8 {
+        1 << (*self as usize)
+    }
+}
+
 /// A controller stores the internal state as a series of known actions.
 ///
 /// The active status of each action is stored, along with how long each action
@@ -97,6 +106,27 @@ impl Controller {
         }
     }
 
+    /// Set the active state of all actions from a bitset.
+    ///
+    /// Any action whose bit is set is activated, and all others are
+    /// deactivated. See `Action::bit` for the bit layout.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::controller::{Action, Controller};
+    ///
+    /// let mut controller = Controller::new();
+    /// controller.activate_bits(Action::MoveLeft.bit() | Action::Hold.bit());
+    ///
+    /// assert!(controller.active(Action::MoveLeft));
+    /// assert!(!controller.active(Action::MoveRight));
+    /// ```
+    pub fn activate_bits(&mut self, bits: u8) {
+        for i in 0..self.active.len() {
+            self.active[i] = bits & Action::from(i).bit() != 0;
+        }
+    }
+
     /// Update all active actions and increment their timers.
     ///
     /// ## Examples
@@ -132,7 +162,7 @@ mod tests {
         let mut controller = Controller::new();
 
         controller.activate(Action::MoveLeft);
-        assert_eq!(controller.active[Action::MoveLeft as usize], true);
+        assert!(controller.active[Action::MoveLeft as usize]);
         assert_eq!(controller.time[Action::MoveLeft as usize], 0);
 
         controller.update();
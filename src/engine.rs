@@ -12,19 +12,27 @@
 //
 // (omitted real IRS and IHS handling)
 
+use std::cmp;
+use std::mem;
+use std::sync::Arc;
 use std::fs::File;
 use std::io::Read;
 use serde_json;
 
-use block::{self, Block, BlockOptions, Rotation, Direction};
+use block::{self, Block, Rotation, Direction};
 use field::{Field, FieldOptions};
 use controller::{Controller, Action};
-use randomizer::{self, Randomizer};
+use randomizer::{self, Randomizer, SequenceRandomizer, WeightedRandomizer, Exhausted, Context};
 use wallkick::{self, Wallkick};
+use clear_rule::{self, ClearRule, Removal};
 use statistics::Statistics;
 use history::History;
+use hold::{Hold, HoldOptions};
 use utility::BlockHelper;
 use rotation_system::{self, RotationSystem};
+use movegen::{self, Input, Move};
+use finesse;
+use piece_set::{self, PieceSet};
 
 /// The current `Engine` status.
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -82,6 +90,12 @@ struct EngineInternal {
 
     /// How long has the current piece been alive?
     piece_timer: u64,
+
+    /// At which tick was the current piece spawned?
+    piece_start: u64,
+
+    /// Where are pieces spawned, if not at the field spawn?
+    spawn: Option<(i32, i32)>,
 }
 
 
@@ -89,35 +103,38 @@ struct EngineInternal {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EngineSettings {
     /// How many ms should are last for
-    are: u64,
+    pub are: u64,
 
     /// Auto-repeat-rate (in ms)
-    arr: u64,
+    pub arr: u64,
 
     /// Delayed auto-shift (in ms)
-    das: u64,
+    pub das: u64,
 
     /// How fast soft drop occurs (cells per ms)
-    soft_drop_speed: f64,
+    pub soft_drop_speed: f64,
 
     /// How long the lock delay exists for
-    lock_delay: u64,
+    pub lock_delay: u64,
 
     /// How many times can we hold per block
-    hold_limit: u64,
+    pub hold_limit: u64,
+
+    /// How many upcoming pieces are shown, at most the randomizer lookahead
+    pub preview_count: usize,
 
     /// How many frames moved per ms
-    gravity: f64,
+    pub gravity: f64,
 
     /// Should gravity be performed before move?
-    gravity_before_move: bool,
+    pub gravity_before_move: bool,
 }
 
 impl Default for EngineSettings {
     fn default() -> EngineSettings {
         EngineSettings {
             are: 0, arr: 16, das: 180, soft_drop_speed: 2f64,
-            lock_delay: 300, hold_limit: 1, gravity: 0.001,
+            lock_delay: 300, hold_limit: 1, preview_count: 3, gravity: 0.001,
             gravity_before_move: false
         }
     }
@@ -134,10 +151,41 @@ pub struct EngineOptions {
 
     pub randomizer_lookahead: usize,
 
+    /// The pieces of each bag for the `nbag` randomizer, which may repeat.
+    /// If empty, each piece of the piece set is used once.
+    pub randomizer_bag: Vec<String>,
+
+    /// The relative weight of each piece for the `weighted` randomizer. If
+    /// empty, each piece of the piece set has the same weight.
+    pub randomizer_weights: Vec<(String, f64)>,
+
+    /// Should the `weighted` randomizer never start with an S or Z?
+    pub randomizer_no_sz_first: bool,
+
+    /// A fixed sequence of pieces played before the randomizer, parsed with
+    /// `randomizer::parse_sequence`. If empty, the randomizer is used from
+    /// the start.
+    pub randomizer_sequence: String,
+
+    /// What happens once `randomizer_sequence` is exhausted. With
+    /// `Exhausted::Fallback` the randomizer is used.
+    pub sequence_exhausted: Exhausted,
+
+    /// How pieces are held
+    pub hold_options: HoldOptions,
+
+    pub piece_set_name: String,
+
+    /// The rotation system used. This must have data for every piece which
+    /// can be generated, such as `generic` for pentominoes.
     pub rotation_system_name: String,
 
     pub wallkick_name: String,
 
+    pub clear_rule_name: String,
+
+    pub players: usize,
+
     pub mspt: u64,
 
     pub engine_settings: EngineSettings
@@ -149,8 +197,17 @@ impl Default for EngineOptions {
             field_options: FieldOptions { ..Default::default() },
             randomizer_name: "bag".to_string(),
             randomizer_lookahead: 7,
+            randomizer_bag: Vec::new(),
+            randomizer_weights: Vec::new(),
+            randomizer_no_sz_first: false,
+            randomizer_sequence: String::new(),
+            sequence_exhausted: Exhausted::Fallback,
+            hold_options: HoldOptions { ..Default::default() },
+            piece_set_name: "tetromino".to_string(),
             rotation_system_name: "srs".to_string(),
             wallkick_name: "srs".to_string(),
+            clear_rule_name: "lines".to_string(),
+            players: 1,
             mspt: 16,
             engine_settings: EngineSettings { ..Default::default() }
         }
@@ -168,6 +225,203 @@ impl EngineOptions {
         f.read_to_string(&mut s).unwrap();
         serde_json::from_str(&s).unwrap()
     }
+
+    /// Construct the randomizer specified by these options.
+    fn randomizer(&self) -> Result<Box<Randomizer>, String> {
+        let randomizer = if self.randomizer_name == "nbag" && !self.randomizer_bag.is_empty() {
+            let names = self.randomizer_bag.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let bag = PieceSet::from_names(&names)?;
+            randomizer::with_pieces(&self.randomizer_name, self.randomizer_lookahead, &bag.pieces)?
+        }
+        else if self.randomizer_name == "weighted" {
+            Box::new(self.weighted_randomizer()?)
+        }
+        else {
+            randomizer::with_piece_set(&self.randomizer_name, self.randomizer_lookahead,
+                                       &self.piece_set_name)?
+        };
+
+        if self.randomizer_sequence.is_empty() {
+            return Ok(randomizer);
+        }
+
+        let sequence = randomizer::parse_sequence(&self.randomizer_sequence)?;
+        let mut sequence = SequenceRandomizer::new(self.randomizer_lookahead, &sequence,
+                                                   self.sequence_exhausted);
+        sequence.fallback = randomizer;
+        Ok(Box::new(sequence))
+    }
+
+    /// Return every piece which can be generated with these options.
+    fn pieces(&self) -> Result<Vec<block::Id>, String> {
+        let mut pieces = piece_set::new(&self.piece_set_name)?.pieces;
+
+        if self.randomizer_name == "nbag" {
+            let names = self.randomizer_bag.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            pieces.extend(PieceSet::from_names(&names)?.pieces);
+        }
+
+        if self.randomizer_name == "weighted" {
+            pieces.extend(self.randomizer_weights.iter().filter_map(|w| block::Id::from_name(&w.0)));
+        }
+
+        pieces.extend(randomizer::parse_sequence(&self.randomizer_sequence)?);
+        Ok(pieces)
+    }
+
+    /// Construct the rotation system specified by these options.
+    ///
+    /// An error is returned if it has no data for a piece which can be
+    /// generated.
+    fn rotation_system(&self) -> Result<Arc<RotationSystem>, String> {
+        let rs = rotation_system::new(&self.rotation_system_name)?;
+        let known = rs.pieces();
+
+        let mut missing = self.pieces()?.into_iter()
+                              .filter(|id| !known.contains(id))
+                              .map(|id| id.name())
+                              .collect::<Vec<_>>();
+        missing.sort();
+        missing.dedup();
+
+        if missing.is_empty() {
+            Ok(rs)
+        }
+        else {
+            Err(format!("rotation system {} has no data for {}",
+                        self.rotation_system_name, missing.join(", ")))
+        }
+    }
+
+    /// Construct the `weighted` randomizer specified by these options.
+    fn weighted_randomizer(&self) -> Result<WeightedRandomizer, String> {
+        let mut randomizer = if self.randomizer_weights.is_empty() {
+            let set = piece_set::new(&self.piece_set_name)?;
+            WeightedRandomizer::with_pieces(self.randomizer_lookahead, &set.pieces)
+        }
+        else {
+            let mut weights = Vec::new();
+            for &(ref name, weight) in &self.randomizer_weights {
+                match block::Id::from_name(name) {
+                    Some(id) if weight >= 0.0 => weights.push((id, weight)),
+                    Some(_) => return Err(format!("negative weight for piece: {}", name)),
+                    None => return Err(format!("unknown piece: {}", name))
+                }
+            }
+
+            if !weights.iter().any(|&(_, weight)| weight > 0.0) {
+                return Err("the weighted randomizer requires a positive weight".to_string());
+            }
+
+            WeightedRandomizer::with_weights(self.randomizer_lookahead, &weights)
+        };
+
+        randomizer.no_sz_first = self.randomizer_no_sz_first;
+        Ok(randomizer)
+    }
+}
+
+/// Events which occur during an `Engine` update.
+///
+/// Events are appended to `Engine::ev` as they occur. These are never removed
+/// by the engine, so a frontend should drain them once handled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A piece was locked using more inputs than the minimum required.
+    FinesseFault {
+        /// Id of the locked piece
+        id: block::Id,
+
+        /// Minimum number of inputs required
+        minimum: usize,
+
+        /// Number of inputs used
+        actual: usize
+    },
+
+    /// Rows or cells were removed from the field.
+    Clear {
+        /// The rows and cells removed by each successive clear
+        removed: Vec<Removal>
+    }
+}
+
+/// A function which can veto or transform a clear before it is applied.
+///
+/// See `Engine::clear_hook`.
+pub type ClearHook = Box<FnMut(&Field, Removal) -> Removal>;
+
+/// A function which scores the best placement of a block on a field, where a
+/// higher score is better.
+///
+/// See `Engine::score_hook`.
+pub type ScoreHook = Box<Fn(&Field, &Block, &Wallkick, &ClearRule) -> f64>;
+
+/// A target resting position for the active piece.
+///
+/// This is used by `Engine::place` to drive the engine without simulating
+/// keyboard timing manually.
+///
+/// ```text
+/// Placement {
+///     x: 0,                 // Final x-coordinate of the block
+///     r: Rotation::R90,     // Final rotation of the block
+///     hold: false,          // Should a hold be performed first?
+///     spin: false           // Must the final input be a rotation?
+/// }
+/// ```
+///
+/// If `spin` is set, the block is soft dropped as required and the last input
+/// before locking will be a rotation. The lowest such resting position is
+/// used. Otherwise, the block is only shifted and rotated before being hard
+/// dropped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(missing_docs)]
+pub struct Placement {
+    pub x: i32,
+
+    pub r: Rotation,
+
+    pub hold: bool,
+
+    pub spin: bool
+}
+
+/// The state of an additional player sharing the `Engine` field.
+///
+/// Each player has their own active block, controller, hold and randomizer.
+/// The active blocks of all players collide with each other.
+pub struct Player {
+    /// Controller used by this player
+    pub co: Controller,
+
+    /// The randomizer used by this player
+    pub rd: Box<Randomizer>,
+
+    /// The active block of this player
+    pub bk: Block,
+
+    /// The held pieces of this player
+    pub hd: Hold,
+
+    /// The input history of this player
+    pub hs: History,
+
+    /// Private internal state flags
+    it: EngineInternal,
+
+    /// The current player status
+    status: Status,
+
+    /// Status of the last frame
+    last_status: Status
+}
+
+impl Player {
+    /// Returns true if the active block of this player is in play.
+    fn piece_active(&self) -> bool {
+        !self.it.need_piece && self.status == Status::Move
+    }
 }
 
 /// Stores the internal engine details.
@@ -176,6 +430,9 @@ impl EngineOptions {
 /// to reduce the overall complexity and namespace various features.
 ///
 /// Most names are condensed a lot to provide shorter references.
+///
+/// The fields `co`, `rd`, `bk`, `hd` and `hs` belong to the first player. Any
+/// further players (e.g. for doubles) are stored in `pl`.
 pub struct Engine {
     /// Controller which is used by the engine
     pub co: Controller,
@@ -184,10 +441,26 @@ pub struct Engine {
     pub rd: Box<Randomizer>,
 
     /// The wallkick object being used.
-    pub wk: &'static Wallkick,
+    pub wk: Arc<Wallkick>,
 
     /// The rotation system used by this engine.
-    pub rs: &'static RotationSystem,
+    pub rs: Arc<RotationSystem>,
+
+    /// The rule which decides what is cleared from the field.
+    pub cr: &'static ClearRule,
+
+    /// Called with each removal found by the clear rule before it is applied.
+    ///
+    /// This allows a game mode to veto a clear (by returning an empty
+    /// `Removal`) or transform it without implementing a new rule.
+    pub clear_hook: Option<ClearHook>,
+
+    /// A function which scores pieces for an adaptive randomizer.
+    ///
+    /// Without a hook, adaptive randomizers such as `friendly` and `evil`
+    /// choose pieces at random. `Evaluator::best_score` scores pieces as the
+    /// bot would.
+    pub score_hook: Option<ScoreHook>,
 
     /// The field which the game is played on
     pub fd: Field,
@@ -195,8 +468,8 @@ pub struct Engine {
     /// The active block
     pub bk: Block,
 
-    /// The current hold block (this doesn't store an actual block right now)
-    pub hd: Option<block::Id>,
+    /// The held pieces
+    pub hd: Hold,
 
     /// Settings used internally by the engine
     pub op: EngineSettings,
@@ -207,9 +480,15 @@ pub struct Engine {
     /// The input history of the game
     pub hs: History,
 
+    /// Events which have occurred and not yet been handled
+    pub ev: Vec<GameEvent>,
+
     /// Is the game running
     pub running: bool,
 
+    /// Any additional players sharing the field
+    pub pl: Vec<Player>,
+
     /// How many milliseconds occur per game tick.
     pub mspt: u64,
 
@@ -256,15 +535,59 @@ impl Engine {
     ///
     /// Each call to update is expected to take place in `~mspt` ms. It
     /// is up to the caller to manage the update lengths appropriately.
+    ///
+    /// Each player is updated in turn. Line clears are only performed once
+    /// every player has moved, so pieces locked on the same tick are cleared
+    /// together.
     pub fn update(&mut self) {
-        self.co.update();
-        self.last_status = self.status;
-
-        if self.co.active(Action::Quit) {
+        if self.co.active(Action::Quit) || self.pl.iter().any(|p| p.co.active(Action::Quit)) {
             self.running = false;
             return;
         }
 
+        self.fd.time = self.tick_count;
+
+        self.update_player();
+        for i in 0..self.pl.len() {
+            self.swap_player(i);
+            self.update_player();
+            self.swap_player(i);
+        }
+
+        self.check_clear();
+        self.tick_count += 1;
+    }
+
+    /// Exchange the state of the first player with the player `self.pl[i]`.
+    ///
+    /// This allows all player logic to be written against the first player.
+    fn swap_player(&mut self, i: usize) {
+        let player = &mut self.pl[i];
+        mem::swap(&mut self.co, &mut player.co);
+        mem::swap(&mut self.rd, &mut player.rd);
+        mem::swap(&mut self.bk, &mut player.bk);
+        mem::swap(&mut self.hd, &mut player.hd);
+        mem::swap(&mut self.hs, &mut player.hs);
+        mem::swap(&mut self.it, &mut player.it);
+        mem::swap(&mut self.status, &mut player.status);
+        mem::swap(&mut self.last_status, &mut player.last_status);
+    }
+
+    /// Update the state of the current player.
+    ///
+    /// The active blocks of all other players collide for the duration of
+    /// the update.
+    fn update_player(&mut self) {
+        self.co.update();
+        self.hs.update(&self.co);
+        self.last_status = self.status;
+
+        let others = self.pl.iter()
+                            .filter(|p| p.piece_active())
+                            .map(|p| p.bk.clone())
+                            .collect::<Vec<_>>();
+        self.fd.set_active(&others);
+
         if self.it.need_piece {
             self.do_piece_spawn();
             self.it.need_piece = false;
@@ -276,6 +599,7 @@ impl Engine {
             self.it.lock_timer = 0;
             self.it.soft_drop_counter = 0f64;
             self.it.gravity_counter = 0f64;
+            self.it.piece_start = self.tick_count;
         }
 
         match self.status {
@@ -292,6 +616,216 @@ impl Engine {
         else {
             self.it.status_timer += 1;
         }
+
+        self.fd.set_active(&[]);
+    }
+
+    /// Perform a single update with the specified actions held.
+    ///
+    /// `actions` is a bitset of `Action`'s (see `Action::bit`). Any action
+    /// not present in the set is released before the update occurs.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::controller::Action;
+    ///
+    /// let mut engine = Engine::new(EngineOptions { ..Default::default() });
+    ///
+    /// // Hold left and rotate on the same tick
+    /// engine.step(Action::MoveLeft.bit() | Action::RotateRight.bit());
+    /// engine.step(0);
+    /// ```
+    pub fn step(&mut self, actions: u8) {
+        self.co.activate_bits(actions);
+        self.update();
+    }
+
+    /// Move the active piece to the specified placement and lock it.
+    ///
+    /// An input sequence is searched for which reaches the placement from the
+    /// current block position. This is then applied through `step` so all
+    /// usual engine logic is performed. Gravity may move the block while this
+    /// happens, so the remaining inputs are searched again from the live block
+    /// whenever it leaves the path. On success, the game is advanced until the next
+    /// piece is active.
+    ///
+    /// An error is returned if the placement is unreachable or the game has
+    /// ended.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::engine::Placement;
+    ///
+    /// let mut engine = Engine::new(EngineOptions { ..Default::default() });
+    ///
+    /// engine.place(Placement {
+    ///     x: 0, r: Rotation::R0, hold: false, spin: false
+    /// }).unwrap();
+    /// ```
+    pub fn place(&mut self, placement: Placement) -> Result<(), String> {
+        self.advance()?;
+
+        if placement.hold {
+            if !self.can_hold() {
+                return Err("hold is not available".to_string());
+            }
+
+            self.tap(Action::Hold);
+        }
+
+        let mut target = match self.find_path(placement) {
+            Some(target) => target,
+            None => return Err(format!("unreachable placement: {:?}", placement))
+        };
+
+        // The piece may lock early and be replaced while inputs are applied
+        let start = self.it.piece_start;
+        let mut inputs = target.inputs.clone();
+        while !inputs.is_empty() {
+            let input = inputs.remove(0);
+            let mut expected = self.bk.clone();
+            input.apply(&mut expected, &self.fd, &*self.wk);
+
+            match input {
+                Input::Left => self.tap(Action::MoveLeft),
+                Input::Right => self.tap(Action::MoveRight),
+                Input::RotateLeft => self.tap(Action::RotateLeft),
+                Input::RotateRight => self.tap(Action::RotateRight),
+
+                // Movement is held until the block can no longer move
+                Input::SoftDrop => self.hold_until_blocked(Action::MoveDown, (0, 1)),
+                Input::DasLeft => self.hold_until_blocked(Action::MoveLeft, (-1, 0)),
+                Input::DasRight => self.hold_until_blocked(Action::MoveRight, (1, 0))
+            }
+
+            if !self.piece_active() || self.it.piece_start != start {
+                return Err(format!("failed to reach placement: {:?}", placement));
+            }
+
+            // The block has moved off the path, so search again from where it is
+            if (self.bk.x, self.bk.y, self.bk.r) != (expected.x, expected.y, expected.r) {
+                target = match self.find_path(placement) {
+                    Some(target) => target,
+                    None => return Err(format!("failed to reach placement: {:?}", placement))
+                };
+                inputs = target.inputs.clone();
+            }
+        }
+
+        if self.bk.ghost(&self.fd).cells() != target.block.cells() {
+            return Err(format!("failed to reach placement: {:?}", placement));
+        }
+
+        self.tap(Action::HardDrop);
+        self.advance()
+    }
+
+    /// Returns true if a piece is currently active and can be moved.
+    pub fn piece_active(&self) -> bool {
+        self.running && !self.it.need_piece && self.status == Status::Move
+    }
+
+    /// Return the upcoming pieces shown to the player, in order.
+    ///
+    /// This is the configured number of preview pieces, limited by the
+    /// randomizer lookahead. Fewer pieces are returned if the randomizer is
+    /// about to end the game. A piece held with `HoldMode::Preview` is the
+    /// first piece.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut engine = Engine::new(EngineOptions { ..Default::default() });
+    /// let queue = engine.next_queue();
+    ///
+    /// assert_eq!(queue.len(), 3);
+    /// assert_eq!(engine.rd.next(), queue[0]);
+    /// ```
+    pub fn next_queue(&mut self) -> Vec<block::Id> {
+        let mut queue = self.hd.queued();
+        queue.truncate(self.op.preview_count);
+
+        let count = cmp::min(self.op.preview_count - queue.len(), self.rd.max_preview());
+        queue.extend(self.rd.preview(count).into_iter()
+                         .take_while(|&id| id != block::Id::None));
+        queue
+    }
+
+    /// Returns true if the active piece can currently be held.
+    pub fn can_hold(&self) -> bool {
+        self.hd.enabled() && self.it.hold_count < self.op.hold_limit
+    }
+
+    /// Press an action for a single tick and then release it.
+    fn tap(&mut self, action: Action) {
+        self.step(action.bit());
+        self.step(0);
+    }
+
+    /// Hold an action until the active block collides at `offset`, and then
+    /// release it.
+    fn hold_until_blocked(&mut self, action: Action, (x, y): (i32, i32)) {
+        let offset = (x * self.bk.scale, y * self.bk.scale);
+
+        while self.piece_active() && !self.bk.collides_at_offset(&self.fd, offset) {
+            self.step(action.bit());
+        }
+        self.step(0);
+    }
+
+    /// Update the engine with no input until a piece is active.
+    ///
+    /// An error is returned if the game has ended.
+    pub fn advance(&mut self) -> Result<(), String> {
+        while self.running && (self.it.need_piece || self.status == Status::Are) {
+            self.step(0);
+        }
+
+        if self.piece_active() {
+            Ok(())
+        }
+        else {
+            Err("game is not running".to_string())
+        }
+    }
+
+    /// Search for the move with the shortest input sequence which reaches the
+    /// specified placement from the active block.
+    ///
+    /// If a spin is requested, the lowest resting position is chosen,
+    /// otherwise the highest is. A hold in the placement is ignored.
+    pub fn find_path(&self, placement: Placement) -> Option<Move> {
+        let (_, ymin) = self.rs.min(self.bk.id, placement.r);
+
+        movegen::generate(&self.fd, &self.bk, &*self.wk).into_iter()
+            .filter(|m| m.spin == placement.spin)
+            .filter(|m| {
+                // The equivalent block in the requested orientation. This
+                // may differ from the found block for symmetric pieces.
+                let cells = m.block.cells();
+                let mut block = m.block.clone();
+                block.x = placement.x;
+                block.y = cells.iter().map(|&(_, y)| y).min().unwrap() - i32!(ymin) * block.scale;
+                block.r = placement.r;
+                block.cells() == cells
+            })
+            .fold(None, |best: Option<Move>, m| {
+                match best {
+                    Some(b) => {
+                        if (placement.spin && m.top() > b.top()) ||
+                                (!placement.spin && m.top() < b.top()) {
+                            Some(m)
+                        }
+                        else {
+                            Some(b)
+                        }
+                    },
+                    None => Some(m)
+                }
+            })
     }
 
     /// High-level move function. This should be easy enough to follow.
@@ -314,7 +848,7 @@ impl Engine {
                 // Possible limitation: Wallkick up could have the y-axis outside
                 // of the allowed field region. Technically should have an infinite
                 // upper region.
-                self.bk.rotate_with_wallkick(&self.fd, self.wk, self.it.irs_rotation);
+                self.bk.rotate_with_wallkick(&self.fd, &*self.wk, self.it.irs_rotation);
                 self.it.irs_flag = false;
                 self.it.irs_rotation = Rotation::R0;
             }
@@ -350,13 +884,46 @@ impl Engine {
         // is deemed as locking.
         self.check_lock();
 
-        // Check line clear
-        self.fd.clear_lines();
-
         // Update the current piece timer
         self.it.piece_timer += 1;
     }
 
+    /// Clear the field using the clear rule and hook.
+    ///
+    /// Rows may move down into the active block of another player. Any such
+    /// block is moved upwards until it is free.
+    fn check_clear(&mut self) {
+        let rule = self.cr;
+        let hook = &mut self.clear_hook;
+        let clear = self.fd.clear_with(|field| {
+            let removal = rule.find(field);
+            match *hook {
+                Some(ref mut hook) => hook(field, removal),
+                None => removal
+            }
+        });
+
+        self.st.lines += clear.lines as u64;
+        self.st.max_chain = cmp::max(self.st.max_chain, clear.chain as u64);
+
+        if clear.chain == 0 {
+            return;
+        }
+
+        self.ev.push(GameEvent::Clear { removed: clear.removed });
+
+        let fd = &self.fd;
+        let first = if self.piece_active() { Some(&mut self.bk) } else { None };
+        let blocks = first.into_iter()
+                          .chain(self.pl.iter_mut().filter(|p| p.piece_active()).map(|p| &mut p.bk));
+
+        for bk in blocks {
+            while bk.collides(fd) && bk.y > -i32!(fd.height) {
+                bk.y -= 1;
+            }
+        }
+    }
+
 
     /// Perform ARE frame
     fn stat_are(&mut self) {
@@ -388,30 +955,88 @@ impl Engine {
         self.running = false;
     }
 
-    /// Perform a hold, swapping the current piece with the hold piece.
+    /// Perform a hold, following the hold mode.
     ///
-    /// If no hold piece is set, then the piece is taken from the randomizer.
+    /// If the hold does not return a piece, then the piece is taken from the
+    /// randomizer.
     fn do_hold(&mut self) {
-        if self.hd.is_none() {
-            self.hd = Some(self.bk.id);
-            self.bk = Block::with_options(self.rd.next(), &self.fd,
-                BlockOptions { rotation_system: self.rs, ..Default::default() }
-            );
+        if !self.hd.enabled() {
+            return;
         }
-        else {
-            let tmp = self.bk.id;
-            self.bk = Block::with_options(self.hd.unwrap(), &self.fd,
-                BlockOptions { rotation_system: self.rs, ..Default::default() }
-            );
-            self.hd = Some(tmp);
+
+        self.it.piece_start = self.tick_count;
+
+        match self.hd.push(self.bk.id, self.bk.r) {
+            Some((id, r)) => self.spawn_held(id, r),
+            None => {
+                if let Some(id) = self.next_piece() {
+                    self.bk = self.spawn_block(id);
+                }
+            }
         }
     }
 
-    /// Retrieve the next piece from the bag and set the current piece to this.
+    /// Retrieve the next piece and set the current piece to this.
+    ///
+    /// A piece held with `HoldMode::Preview` is spawned before the randomizer
+    /// is used.
     fn do_piece_spawn(&mut self) {
-        self.bk = Block::with_options(self.rd.next(), &self.fd,
-            BlockOptions { rotation_system: self.rs, ..Default::default() }
-        );
+        if let Some((id, r)) = self.hd.pop_queued() {
+            self.spawn_held(id, r);
+        }
+        else if let Some(id) = self.next_piece() {
+            self.bk = self.spawn_block(id);
+        }
+    }
+
+    /// Set the current piece to a piece taken from the hold.
+    ///
+    /// If rotations are kept, the piece is rotated into its held rotation as
+    /// with an initial rotation.
+    fn spawn_held(&mut self, id: block::Id, r: Rotation) {
+        self.bk = self.spawn_block(id);
+
+        if !self.hd.op.reset_rotation && r != self.bk.r {
+            let rotation = Rotation::from((4 + r as usize - self.bk.r as usize) % 4);
+            self.bk.rotate_with_wallkick(&self.fd, &*self.wk, rotation);
+        }
+    }
+
+    /// Retrieve the next piece from the randomizer.
+    ///
+    /// If the randomizer is exhausted the game is over and `None` is returned.
+    fn next_piece(&mut self) -> Option<block::Id> {
+        let id = match self.score_hook {
+            Some(ref hook) => {
+                let (fd, rs, wk, cr, spawn) = (&self.fd, &self.rs, &*self.wk, self.cr, self.it.spawn);
+                let score = |id| hook(fd, &Block::spawn(id, fd, spawn, rs.clone()), wk, cr);
+                self.rd.next_with(&Context { score: &score })
+            }
+            None => self.rd.next()
+        };
+
+        if id == block::Id::None {
+            self.status = Status::GameOver;
+            None
+        }
+        else {
+            Some(id)
+        }
+    }
+
+    /// Construct a block of the specified type at the current player spawn
+    /// on `field`, following the spawn rule of the rotation system.
+    ///
+    /// This allows blocks to be spawned on a copy of the engine field, such
+    /// as one searched by a bot.
+    pub fn spawn_on(&self, id: block::Id, field: &Field) -> Block {
+        Block::spawn(id, field, self.it.spawn, self.rs.clone())
+    }
+
+    /// Construct a block of the specified type at the current player spawn,
+    /// following the spawn rule of the rotation system.
+    fn spawn_block(&self, id: block::Id) -> Block {
+        self.spawn_on(id, &self.fd)
     }
 
     /// Check for a lockout with the current piece.
@@ -421,7 +1046,7 @@ impl Engine {
 
     /// Check if a hold action is present and if so try to perform a hold.
     fn check_hold(&mut self) -> bool {
-        if self.co.time(Action::Hold) == 1 && self.it.hold_count < self.op.hold_limit {
+        if self.co.time(Action::Hold) == 1 && self.can_hold() {
             self.do_hold();
             self.it.hold_count += 1;
             true
@@ -467,11 +1092,11 @@ impl Engine {
     fn check_rotate(&mut self) -> bool {
         let mut r = false;
         if self.co.time(Action::RotateLeft) == 1 {
-            self.bk.rotate_with_wallkick(&self.fd, self.wk, Rotation::R270);
+            self.bk.rotate_with_wallkick(&self.fd, &*self.wk, Rotation::R270);
             r = true;
         }
         if self.co.time(Action::RotateRight) == 1 {
-            self.bk.rotate_with_wallkick(&self.fd, self.wk, Rotation::R90);
+            self.bk.rotate_with_wallkick(&self.fd, &*self.wk, Rotation::R90);
             r = true;
         }
 
@@ -560,6 +1185,9 @@ impl Engine {
         // Lock the piece if instant lock or over lock delay.
         // Manage the next state to go to since this block is done.
         if (self.it.lock_timer > self.ticks(self.op.lock_delay)) || instant_lock {
+            self.check_finesse();
+            self.st.pieces += 1;
+
             // Clone is not ideal
             // Freezing here places a render frame between this and spawning
             // of a piece. This causes an overlap in the field and block
@@ -585,21 +1213,65 @@ impl Engine {
     }
 
 
+    /// Compare the inputs used for the active piece against the minimum
+    /// required and record any finesse faults.
+    ///
+    /// This must be called before the active piece is frozen.
+    fn check_finesse(&mut self) {
+        let spawn = self.spawn_block(self.bk.id);
+
+        // Pieces which require a soft drop have no finesse value
+        let minimum = match finesse::minimum(&self.fd, &spawn, &self.bk, &*self.wk) {
+            Some(minimum) => minimum,
+            None => return
+        };
+
+        let actual = [Action::MoveLeft, Action::MoveRight, Action::RotateLeft, Action::RotateRight]
+                        .iter()
+                        .map(|&action| self.hs.presses_since(self.it.piece_start, action))
+                        .sum();
+
+        let faults = finesse::faults(minimum, actual);
+        if faults > 0 {
+            self.st.finesse_faults += faults as u64;
+            self.ev.push(GameEvent::FinesseFault {
+                id: self.bk.id, minimum, actual
+            });
+        }
+    }
+
     /// Construct a new `Engine` from an `EngineOptions` instance.
+    ///
+    /// With multiple players, the first player spawns at the field spawn and
+    /// each following player spawns `width / players` columns further right.
+    /// A spawn region of the field only applies to the first player.
     pub fn new(options: EngineOptions) -> Engine {
+        // Each player draws from their own randomizer
+        let mut randomizers = (0..cmp::max(options.players, 1))
+                                  .map(|_| options.randomizer().unwrap())
+                                  .collect::<Vec<_>>()
+                                  .into_iter();
+
+        let rs = options.rotation_system().unwrap();
+
         let mut engine = Engine {
             fd: Field::with_options(options.field_options),
-            rd: randomizer::new(&options.randomizer_name, options.randomizer_lookahead).unwrap(),
+            rd: randomizers.next().unwrap(),
             co: Controller::new(),
-            rs: rotation_system::new(&options.rotation_system_name).unwrap(),
+            rs,
             wk: wallkick::new(&options.wallkick_name).unwrap(),
-            bk: Block { id: block::Id::None, x: 0, y: 0, r: Rotation::R0, rs: rotation_system::new("srs").unwrap() },
-            hd: None,
+            cr: clear_rule::new(&options.clear_rule_name).unwrap(),
+            clear_hook: None,
+            score_hook: None,
+            bk: Block { id: block::Id::None, x: 0, y: 0, r: Rotation::R0, rs: rotation_system::new("srs").unwrap(), scale: 1 },
+            hd: Hold::new(options.hold_options.clone()),
             tick_count: 0,
             mspt: options.mspt,
             running: true,
+            pl: Vec::new(),
             op: options.engine_settings,
             hs: History::new(),
+            ev: Vec::new(),
             st: Statistics::new(),
             it: EngineInternal { ..Default::default() },
             status: Status::Move,
@@ -607,6 +1279,427 @@ impl Engine {
         };
 
         engine.it.need_piece = true;
+
+        for i in 1..options.players {
+            let (x, y) = engine.fd.spawn;
+            let x = x + i32!(i * engine.fd.width / options.players);
+
+            engine.pl.push(Player {
+                co: Controller::new(),
+                rd: randomizers.next().unwrap(),
+                bk: engine.bk.clone(),
+                hd: Hold::new(options.hold_options.clone()),
+                hs: History::new(),
+                it: EngineInternal {
+                    need_piece: true, spawn: Some((x, y)), ..Default::default()
+                },
+                status: Status::Move,
+                last_status: Status::Move
+            });
+        }
+
         engine
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bot::Evaluator;
+
+    #[test]
+    fn test_place() {
+        let mut engine = Engine::new(EngineOptions { ..Default::default() });
+
+        for _ in 0..5 {
+            engine.place(Placement {
+                x: 0, r: Rotation::R0, hold: false, spin: false
+            }).unwrap();
+        }
+
+        let cells = iproduct!(0..engine.fd.width, 0..engine.fd.height)
+                        .filter(|&(x, y)| engine.fd.occupies((x, y)))
+                        .count();
+
+        assert_eq!(cells, 5 * 4);
+        assert!(engine.bk.id != block::Id::None);
+    }
+
+    #[test]
+    fn test_place_gravity() {
+        // The block falls while it is moved, and at 20G is always on the floor
+        for &gravity in &[0.05, 2.0] {
+            let mut engine = Engine::new(EngineOptions {
+                engine_settings: EngineSettings { gravity, ..Default::default() },
+                randomizer_sequence: "IJLOST".to_string(),
+                ..Default::default()
+            });
+
+            for &(x, r) in &[(0, Rotation::R0), (7, Rotation::R90), (3, Rotation::R180),
+                             (5, Rotation::R270), (1, Rotation::R90), (6, Rotation::R0)] {
+                engine.place(Placement { x, r, hold: false, spin: false }).unwrap();
+            }
+
+            assert_eq!(engine.st.pieces, 6);
+        }
+    }
+
+    #[test]
+    fn test_finesse() {
+        let mut engine = Engine::new(EngineOptions { ..Default::default() });
+        engine.advance().unwrap();
+
+        engine.bk = engine.spawn_block(block::Id::T);
+
+        // Tapping left three times instead of a single DAS
+        engine.place(Placement {
+            x: 0, r: Rotation::R0, hold: false, spin: false
+        }).unwrap();
+
+        assert_eq!(engine.st.pieces, 1);
+        assert_eq!(engine.st.finesse_faults, 2);
+        assert_eq!(engine.ev, vec![GameEvent::FinesseFault {
+            id: block::Id::T, minimum: 1, actual: 3
+        }]);
+    }
+
+    #[test]
+    fn test_finesse_das() {
+        let mut engine = Engine::new(EngineOptions { ..Default::default() });
+        engine.advance().unwrap();
+        engine.bk = engine.spawn_block(block::Id::I);
+
+        // Holding left across the das is a single press
+        for _ in 0..40 {
+            engine.step(Action::MoveLeft.bit());
+        }
+        engine.step(0);
+        engine.step(Action::HardDrop.bit());
+        engine.advance().unwrap();
+
+        assert_eq!(engine.st.pieces, 1);
+        assert_eq!(engine.st.finesse_faults, 0);
+        assert!(engine.ev.is_empty());
+    }
+
+    #[test]
+    fn test_place_spin() {
+        let mut engine = Engine::new(EngineOptions { ..Default::default() });
+        engine.advance().unwrap();
+
+        let rows = [
+            (22, "#  #      "),
+            (23, "#   ######"),
+            (24, "## #######"),
+        ];
+
+        for &(y, row) in rows.iter() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    engine.fd.set((x, y), block::Id::I);
+                }
+            }
+        }
+
+        engine.bk = engine.spawn_block(block::Id::T);
+
+        engine.place(Placement {
+            x: 1, r: Rotation::R180, hold: false, spin: true
+        }).unwrap();
+
+        // Both lines are cleared, leaving the overhang row
+        let bottom = (0..engine.fd.width).map(|x| engine.fd.occupies((x, 24)))
+                                         .collect::<Vec<_>>();
+        assert_eq!(bottom, [true, false, false, true, false,
+                            false, false, false, false, false]);
+    }
+
+    #[test]
+    fn test_clear_hook() {
+        let setup = |hook: Option<ClearHook>| {
+            let mut engine = Engine::new(EngineOptions { ..Default::default() });
+            engine.advance().unwrap();
+            engine.clear_hook = hook;
+
+            for x in 4..engine.fd.width {
+                engine.fd.set((x, 24), block::Id::I);
+            }
+
+            engine.bk = engine.spawn_block(block::Id::I);
+
+            engine.place(Placement {
+                x: 0, r: Rotation::R0, hold: false, spin: false
+            }).unwrap();
+
+            engine
+        };
+
+        // Clearing normally reports the removed row
+        let engine = setup(None);
+        assert_eq!(engine.st.lines, 1);
+        assert!(engine.ev.contains(&GameEvent::Clear {
+            removed: vec![Removal { rows: vec![24], cells: vec![] }]
+        }));
+
+        // Veto every clear
+        let engine = setup(Some(Box::new(|_, _| Removal::default())));
+        assert_eq!(engine.st.lines, 0);
+        assert!(engine.fd.is_full(24));
+    }
+
+    #[test]
+    fn test_big() {
+        let mut engine = Engine::new(EngineOptions {
+            field_options: FieldOptions { scale: 2, ..Default::default() },
+            ..Default::default()
+        });
+        engine.advance().unwrap();
+
+        engine.bk = engine.spawn_block(block::Id::I);
+
+        engine.place(Placement {
+            x: 0, r: Rotation::R0, hold: false, spin: false
+        }).unwrap();
+
+        assert!(engine.fd.occupies((7, 23)));
+        assert!(engine.fd.occupies((7, 24)));
+
+        engine.bk = engine.spawn_block(block::Id::I);
+
+        engine.place(Placement {
+            x: 4, r: Rotation::R90, hold: false, spin: false
+        }).unwrap();
+
+        // Rows are cleared in pairs, leaving the rest of the vertical I
+        assert_eq!(engine.st.lines, 2);
+        assert!((19..25).all(|y| engine.fd.rows()[y] == 0b11_0000_0000));
+    }
+
+    #[test]
+    fn test_nbag() {
+        let mut engine = Engine::new(EngineOptions {
+            randomizer_name: "nbag".to_string(),
+            randomizer_bag: vec!["I".to_string(), "I".to_string(), "O".to_string()],
+            ..Default::default()
+        });
+
+        for _ in 0..3 {
+            let mut bag = (0..3).map(|_| engine.rd.next()).collect::<Vec<_>>();
+            bag.sort();
+            assert_eq!(bag, vec![block::Id::I, block::Id::I, block::Id::O]);
+        }
+    }
+
+    #[test]
+    fn test_piece_set() {
+        // SRS has no data for pentominoes
+        let mut options = EngineOptions {
+            piece_set_name: "pentomino".to_string(),
+            ..Default::default()
+        };
+        match options.rotation_system() {
+            Err(e) => assert!(e.starts_with("rotation system srs has no data for"), "{}", e),
+            Ok(_) => panic!("srs has data for pentominoes")
+        }
+
+        options.rotation_system_name = "generic".to_string();
+        let mut engine = Engine::new(options);
+
+        assert!(engine.rs.pieces().contains(&engine.rd.preview(1)[0]));
+        engine.advance().unwrap();
+        assert!(engine.bk.id != block::Id::None);
+
+        let engine = Engine::new(EngineOptions { ..Default::default() });
+        assert_eq!(engine.rs.pieces(), block::Id::variants().to_vec());
+    }
+
+    #[test]
+    fn test_spawn_rule() {
+        let mut engine = Engine::new(EngineOptions {
+            field_options: FieldOptions { width: 9, ..Default::default() },
+            randomizer_sequence: "TIO".to_string(),
+            ..Default::default()
+        });
+
+        // SRS spawns centred above the visible field and drops one row, so
+        // the T covers the middle three columns and the first visible row
+        engine.advance().unwrap();
+        assert_eq!(engine.bk.id, block::Id::T);
+        assert_eq!((engine.bk.x, engine.bk.y), (3, 2));
+
+        // The next piece spawns with the same rule when holding, rounding
+        // left as the I cannot be centred on an odd-width field
+        engine.do_hold();
+        assert_eq!(engine.bk.id, block::Id::I);
+        assert_eq!((engine.bk.x, engine.bk.y), (2, 2));
+        let columns = engine.bk.cells().iter().map(|&(x, _)| x).collect::<Vec<_>>();
+        assert_eq!(columns, vec![2, 3, 4, 5]);
+
+        // As does a held piece taken back from the hold
+        engine.it.hold_count = 0;
+        engine.bk.shift_extend(&engine.fd, Direction::Left);
+        engine.do_hold();
+        assert_eq!(engine.bk.id, block::Id::T);
+        assert_eq!((engine.bk.x, engine.bk.y), (3, 2));
+    }
+
+    #[test]
+    fn test_weighted() {
+        let mut options = EngineOptions {
+            randomizer_name: "weighted".to_string(),
+            randomizer_weights: vec![("S".to_string(), 1.0), ("T".to_string(), 0.0)],
+            randomizer_no_sz_first: true,
+            ..Default::default()
+        };
+
+        // An S cannot be avoided if it is the only piece with any weight
+        let mut randomizer = options.randomizer().unwrap();
+        assert!((0..10).all(|_| randomizer.next() == block::Id::S));
+
+        options.randomizer_weights = vec![("T".to_string(), -1.0)];
+        assert!(options.weighted_randomizer().is_err());
+        options.randomizer_weights = vec![("Q".to_string(), 1.0)];
+        assert!(options.weighted_randomizer().is_err());
+    }
+
+    #[test]
+    fn test_evil() {
+        let mut engine = Engine::new(EngineOptions {
+            randomizer_name: "evil".to_string(),
+            ..Default::default()
+        });
+
+        let evaluator = Evaluator::default();
+        engine.score_hook = Some(Box::new(move |fd, bk, wk, cr| evaluator.best_score(fd, bk, wk, cr)));
+
+        // A well which only an I can fill
+        for y in engine.fd.height - 4..engine.fd.height {
+            for x in 1..engine.fd.width {
+                engine.fd.set((x, y), block::Id::O);
+            }
+        }
+
+        assert!(engine.next_queue().is_empty());
+        for _ in 0..20 {
+            assert!(engine.next_piece() != Some(block::Id::I));
+        }
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut engine = Engine::new(EngineOptions {
+            randomizer_sequence: "IO".to_string(),
+            sequence_exhausted: Exhausted::End,
+            ..Default::default()
+        });
+
+        engine.advance().unwrap();
+        assert_eq!(engine.bk.id, block::Id::I);
+        engine.place(Placement { x: 0, r: Rotation::R0, hold: false, spin: false }).unwrap();
+
+        engine.advance().unwrap();
+        assert_eq!(engine.bk.id, block::Id::O);
+        // The game ends once the sequence is exhausted
+        let result = engine.place(Placement { x: 4, r: Rotation::R0, hold: false, spin: false });
+        assert!(result.is_err());
+        assert!(!engine.running);
+        assert!(engine.fd.occupies((5, 24)));
+    }
+
+    #[test]
+    fn test_hold() {
+        use block::Id;
+        use hold::HoldMode;
+
+        let options = |mode, slots, reset_rotation| EngineOptions {
+            randomizer_sequence: "IOTSZLJ".to_string(),
+            hold_options: HoldOptions { mode, slots, reset_rotation },
+            ..Default::default()
+        };
+        let hold = Placement { x: 3, r: Rotation::R0, hold: true, spin: false };
+
+        let mut engine = Engine::new(options(HoldMode::Disabled, 1, true));
+        engine.advance().unwrap();
+        assert!(!engine.can_hold());
+        assert!(engine.place(hold).is_err());
+
+        // Held pieces are only returned once every slot is filled
+        let mut engine = Engine::new(options(HoldMode::Queue, 2, true));
+        engine.place(hold).unwrap();
+        engine.place(hold).unwrap();
+        assert_eq!(engine.hd.held(), vec![Id::I, Id::T]);
+        assert_eq!(engine.bk.id, Id::Z);
+        engine.place(hold).unwrap();
+        assert_eq!(engine.hd.held(), vec![Id::T, Id::Z]);
+
+        // The held piece is the next piece in the preview
+        let mut engine = Engine::new(options(HoldMode::Preview, 1, true));
+        engine.advance().unwrap();
+        engine.tap(Action::Hold);
+        assert_eq!(engine.bk.id, Id::O);
+        assert_eq!(engine.next_queue(), vec![Id::I, Id::T, Id::S]);
+        engine.place(Placement { hold: false, ..hold }).unwrap();
+        assert_eq!(engine.bk.id, Id::I);
+        assert_eq!(engine.next_queue(), vec![Id::T, Id::S, Id::Z]);
+
+        // A held piece can keep its rotation
+        for &reset_rotation in &[true, false] {
+            let mut engine = Engine::new(options(HoldMode::Swap, 1, reset_rotation));
+            engine.advance().unwrap();
+            engine.tap(Action::RotateRight);
+            engine.tap(Action::Hold);
+            engine.place(Placement { hold: false, ..hold }).unwrap();
+            engine.tap(Action::Hold);
+
+            assert_eq!(engine.bk.id, Id::I);
+            assert_eq!(engine.bk.r == Rotation::R90, !reset_rotation);
+        }
+    }
+
+    #[test]
+    fn test_doubles() {
+        let setup = || {
+            let mut engine = Engine::new(EngineOptions {
+                field_options: FieldOptions { width: 20, ..Default::default() },
+                players: 2,
+                ..Default::default()
+            });
+            engine.update();
+
+            engine.bk = engine.spawn_block(block::Id::I);
+            engine.swap_player(0);
+            engine.bk = engine.spawn_block(block::Id::I);
+            engine.swap_player(0);
+
+            engine
+        };
+
+        // The second player cannot move through the first players block
+        let mut engine = setup();
+        assert_eq!((engine.bk.x, engine.pl[0].bk.x), (3, 13));
+
+        engine.pl[0].co.activate(Action::MoveLeft);
+        for _ in 0..30 {
+            engine.update();
+        }
+        assert_eq!(engine.pl[0].bk.x, 7);
+
+        // Pieces locked on the same tick are cleared together
+        let mut engine = setup();
+        for x in 4..16 {
+            engine.fd.set((x, 24), block::Id::I);
+        }
+
+        engine.bk.x = 0;
+        engine.pl[0].bk.x = 16;
+        engine.co.activate(Action::HardDrop);
+        engine.pl[0].co.activate(Action::HardDrop);
+        engine.update();
+
+        assert_eq!(engine.st.pieces, 2);
+        assert_eq!(engine.st.lines, 1);
+        assert_eq!(engine.ev, vec![GameEvent::Clear {
+            removed: vec![Removal { rows: vec![24], cells: vec![] }]
+        }]);
+    }
+}
@@ -3,13 +3,19 @@
 //! A `Field` manages the state of previously placed blocks. A `Field` is not
 //! aware of blocks themselves for the most part, besides the `freeze` function.
 
+use std::cmp;
+
 use block::{Block, Id};
+use clear_rule::{ClearRule, Removal, Lines};
 
-/// A `Field` is simply a 2-D `Vec` with some corresponding options.
+/// A `Field` is an occupancy bitboard with a corresponding colour layer.
+///
+/// Each row is stored as a bitmask, where bit `x` is set if the cell `(x, y)`
+/// is occupied. This makes collision tests and line clears cheap, which is
+/// important when searching many positions (e.g. in a bot). The `Id` of each
+/// occupied cell is stored separately and is only required for rendering.
 ///
-/// Internally we use a `Vec` of `Vec`'s for easier resizing, but all rows
-/// must be of equal length, so it can be thought of as rectangular for all
-/// purposes.
+/// Since a row is a `u64`, the width of a field can be at most 64.
 ///
 /// ## Note
 ///
@@ -47,8 +53,160 @@ pub struct Field {
     /// The initial spawn of a `Block` on this field.
     pub spawn: (i32, i32),
 
-    /// The current field state.
-    pub data: Vec<Vec<Id>>,
+    /// The area a `Block` spawns in, replacing `spawn` when set.
+    pub spawn_region: Option<SpawnRegion>,
+
+    /// How blocks fall after a line clear.
+    pub gravity: Gravity,
+
+    /// The width and height in cells of each mino of a `Block` on this field.
+    ///
+    /// A scale of 2 gives TGM "Big" mode.
+    pub scale: i32,
+
+    /// How locked cells are displayed.
+    pub visibility: Visibility,
+
+    /// The current time in ticks.
+    ///
+    /// Cells are stamped with this time when they are set. An `Engine` keeps
+    /// this equal to its tick count, so stamps are reproduced exactly when
+    /// replaying a game from its inputs.
+    pub time: u64,
+
+    /// The occupancy bitmask of each row.
+    rows: Vec<u64>,
+
+    /// The `Id` of each cell.
+    data: Vec<Vec<Id>>,
+
+    /// The bitmask of permanently blocked cells in each row.
+    blocked: Vec<u64>,
+
+    /// The bitmask of void cells in each row.
+    void: Vec<u64>,
+
+    /// Does any cell have a mask?
+    masked: bool,
+
+    /// The bitmask of cells occupied by other active blocks in each row.
+    active: Vec<u64>,
+
+    /// The time at which each cell was set.
+    locked: Vec<Vec<u64>>,
+
+    /// The time of the most recent clear.
+    last_clear: Option<u64>,
+}
+
+/// The shape of a single field cell.
+///
+/// Masked cells allow non-rectangular fields. Blocks can never enter a
+/// masked cell, and masked cells are not required to be filled for a row to
+/// be cleared. A masked cell never moves when rows are cleared.
+#[derive(Serialize, Deserialize, Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mask {
+    /// A normal cell which can be filled.
+    Open,
+
+    /// A cell which is permanently filled, e.g. a wall within the field.
+    Blocked,
+
+    /// A cell which is not part of the field at all.
+    Void
+}
+
+/// A rectangular area of a `Field` which blocks spawn in.
+///
+/// Blocks are centred on the columns of the region and the spawn row of the
+/// rotation system is relative to its top row. A block which does not fit
+/// there is moved down through the region until it does. See `Block::spawn`.
+#[derive(Serialize, Deserialize, Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnRegion {
+    /// The leftmost column of the region.
+    pub x: i32,
+
+    /// The top row of the region.
+    pub y: i32,
+
+    /// The number of columns in the region.
+    pub width: usize,
+
+    /// The number of rows in the region.
+    pub height: usize
+}
+
+/// How locked cells of a `Field` are displayed.
+///
+/// This does not affect play. See `Field::appearance`.
+#[derive(Serialize, Deserialize, Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// Every cell is shown.
+    Visible,
+
+    /// Every cell is hidden once locked (e.g. the TGM invisible roll).
+    Invisible,
+
+    /// Cells are hidden once they have been locked for the specified number
+    /// of ticks.
+    Fading(u64),
+
+    /// Cells are hidden until the next clear, which reveals every cell
+    /// locked before it.
+    HiddenUntilClear,
+
+    /// Every cell is shown without its colour.
+    Monochrome
+}
+
+/// How a single cell of a `Field` should currently be displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Appearance {
+    /// The cell is empty.
+    Empty,
+
+    /// The cell is occupied but should not be shown.
+    Hidden,
+
+    /// The cell is occupied and should be shown without colour.
+    Plain,
+
+    /// The cell is occupied and should be shown in the colour of its `Id`.
+    Colour(Id)
+}
+
+/// How blocks fall after a line clear.
+#[derive(Serialize, Deserialize, Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    /// Rows above a cleared line move down by the number of lines cleared
+    /// beneath them. Cells may be left floating.
+    Naive,
+
+    /// Connected groups of cells with the same `Id` fall as units until they
+    /// land. This may complete further lines, which are then cleared.
+    Sticky,
+
+    /// Connected groups of cells fall as units until they land, regardless
+    /// of their `Id`. This may complete further lines, which are then cleared.
+    Cascade
+}
+
+/// The result of clearing a field.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Clear {
+    /// The total number of lines cleared.
+    pub lines: usize,
+
+    /// The number of successive clears which occurred.
+    ///
+    /// This is 0 if nothing was cleared, and is only ever greater than 1
+    /// when using `Gravity::Sticky` or `Gravity::Cascade`.
+    pub chain: usize,
+
+    /// The rows and cells removed by each successive clear.
+    ///
+    /// Positions are those of the field at the time of each clear.
+    pub removed: Vec<Removal>
 }
 
 /// Optional values which can be set when initializing a `Field`.
@@ -60,7 +218,11 @@ pub struct Field {
 ///     width: 10,
 ///     height: 25,
 ///     hidden: 3,
-///     spawn: (4, 0)
+///     spawn: (4, 0),
+///     spawn_region: None,
+///     gravity: Gravity::Naive,
+///     scale: 1,
+///     visibility: Visibility::Visible
 /// }
 /// ```
 ///
@@ -84,7 +246,15 @@ pub struct FieldOptions {
 
     pub hidden: usize,
 
-    pub spawn: (i32, i32)
+    pub spawn: (i32, i32),
+
+    pub spawn_region: Option<SpawnRegion>,
+
+    pub gravity: Gravity,
+
+    pub scale: i32,
+
+    pub visibility: Visibility
 }
 
 impl Default for FieldOptions {
@@ -93,11 +263,64 @@ impl Default for FieldOptions {
             width: 10,
             height: 25,
             hidden: 3,
-            spawn: (4, 0)
+            spawn: (4, 0),
+            spawn_region: None,
+            gravity: Gravity::Naive,
+            scale: 1,
+            visibility: Visibility::Visible
         }
     }
 }
 
+/// Standard analysis metrics of a `Field`.
+///
+/// These are the values commonly used by bots and other evaluators. All
+/// metrics are computed from the occupancy of the field only, and are
+/// returned by `Field::metrics`.
+///
+/// A hole is an empty cell with an occupied cell somewhere above it in the
+/// same column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMetrics {
+    /// The height of each column, measured from the bottom of the field.
+    pub heights: Vec<usize>,
+
+    /// The sum of all column heights.
+    pub aggregate_height: usize,
+
+    /// The number of holes.
+    pub holes: usize,
+
+    /// The number of occupied cells which lie above a hole.
+    pub covered: usize,
+
+    /// The sum of height differences between adjacent columns.
+    pub bumpiness: usize,
+
+    /// The number of filled/empty transitions along each non-empty row.
+    ///
+    /// The walls are considered filled.
+    pub row_transitions: usize,
+
+    /// The number of filled/empty transitions along each column.
+    ///
+    /// The floor is considered filled.
+    pub column_transitions: usize,
+
+    /// The depth of each column relative to the lower of its neighbours.
+    ///
+    /// The walls are considered infinitely high.
+    pub wells: Vec<usize>,
+
+    /// The column of the deepest well, if any well exists.
+    pub deepest_well: Option<usize>,
+
+    /// The centre of each T-slot on the field.
+    ///
+    /// See `Field::is_t_slot`.
+    pub t_slots: Vec<(usize, usize)>,
+}
+
 impl Field {
     /// Construct a `Field` object with default values.
     pub fn new() -> Field {
@@ -117,30 +340,284 @@ impl Field {
     ///             });
     /// ```
     pub fn with_options(options: FieldOptions) -> Field {
+        assert!(options.width <= 64, "field width must be at most 64");
+
         Field {
             width: options.width,
             height: options.height,
             hidden: options.hidden,
             spawn: options.spawn,
-            data: vec![vec![Id::None; options.width]; options.height]
+            spawn_region: options.spawn_region,
+            gravity: options.gravity,
+            scale: options.scale,
+            visibility: options.visibility,
+            time: 0,
+            rows: vec![0; options.height],
+            data: vec![vec![Id::None; options.width]; options.height],
+            blocked: vec![0; options.height],
+            void: vec![0; options.height],
+            masked: false,
+            active: vec![0; options.height],
+            locked: vec![vec![0; options.width]; options.height],
+            last_clear: None
         }
     }
 
+    /// Return the bitmask of a completely filled row.
+    fn full_row(&self) -> u64 {
+        if self.width == 64 { !0 } else { (1 << self.width) - 1 }
+    }
+
     /// Clear lines from the field and return the number cleared.
+    ///
+    /// This is a convenience function for `clear`, which also reports chains.
     pub fn clear_lines(&mut self) -> usize {
-        // Keep only lines with an empty cell (non-filled)
-        self.data.retain(|ref x| x.iter().any(|&x| x == Id::None));
+        self.clear().lines
+    }
+
+    /// Clear lines from the field using its `Gravity`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::with_options(FieldOptions {
+    ///     gravity: Gravity::Cascade, ..Default::default()
+    /// });
+    ///
+    /// // A floating cell falls into the gap once the bottom row clears
+    /// for x in 1..10 {
+    ///     field.set((x, 24), block::Id::I);
+    ///     field.set((x, 23), block::Id::I);
+    /// }
+    /// field.set((0, 24), block::Id::J);
+    /// field.set((0, 21), block::Id::O);
+    ///
+    /// let clear = field.clear();
+    /// assert_eq!(clear.lines, 2);
+    /// assert_eq!(clear.chain, 2);
+    /// ```
+    pub fn clear(&mut self) -> Clear {
+        self.clear_with(|field| Lines::new().find(field))
+    }
 
-        // Calculate how many lines were cleared
-        let lines = self.height - self.data.len();
+    /// Clear the field using a custom rule.
+    ///
+    /// `find` is called with the current field and returns what should be
+    /// removed. This is repeated after gravity is applied until nothing more
+    /// is removed, so chains are handled for any rule. A removal which
+    /// contains no occupied cells ends the clear, and each row is counted
+    /// once.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// for x in 0..field.width {
+    ///     field.set((x, 24), block::Id::I);
+    /// }
+    /// field.set((0, 23), block::Id::I);
+    ///
+    /// // Only tetrises are cleared
+    /// let rule = clear_rule::new("four").unwrap();
+    /// assert_eq!(field.clear_with(|f| rule.find(f)).lines, 0);
+    /// assert!(field.occupies((0, 24)));
+    /// ```
+    pub fn clear_with<F>(&mut self, mut find: F) -> Clear
+        where F: FnMut(&Field) -> Removal
+    {
+        let mut clear = Clear { lines: 0, chain: 0, removed: Vec::new() };
+
+        loop {
+            let mut removal = find(self);
+            removal.rows.sort();
+            removal.rows.dedup();
 
-        // Sure this isn't optimal, but for a small array and with only 4
-        // pushses max (unless cascading) who would notice?
-        for _ in 0..lines {
-            self.data.insert(0, vec![Id::None; self.width]);
+            // A removal of only empty cells would be found again forever
+            let removes = removal.rows.iter().any(|&y| self.rows[y] != 0) ||
+                          removal.cells.iter().any(|&p| self.occupies(p));
+            if !removes {
+                break;
+            }
+
+            self.remove(&removal);
+
+            clear.lines += removal.rows.len();
+            clear.chain += 1;
+            clear.removed.push(removal);
+
+            self.last_clear = Some(self.time);
+
+            match self.gravity {
+                Gravity::Naive => break,
+                Gravity::Sticky => self.settle(true),
+                Gravity::Cascade => self.settle(false)
+            }
         }
 
-        lines
+        clear
+    }
+
+    /// Return true if every open cell in the specified row is occupied.
+    ///
+    /// A row without any open cells is never full.
+    pub fn is_full(&self, y: usize) -> bool {
+        let masked = self.blocked[y] | self.void[y];
+        masked != self.full_row() && self.rows[y] | masked == self.full_row()
+    }
+
+    /// Remove the rows and cells of `removal` from the field.
+    ///
+    /// Rows above a removed row move down. With `Gravity::Naive`, cells above
+    /// a removed cell also move down within their column. Any other gravity
+    /// is left to `settle`.
+    fn remove(&mut self, removal: &Removal) {
+        let naive = self.gravity == Gravity::Naive;
+
+        // Masked cells never move, so rows cannot be moved as a whole
+        if self.masked || (naive && !removal.cells.is_empty()) {
+            let mut removed = vec![0u64; self.height];
+            for &y in &removal.rows {
+                removed[y] = self.full_row();
+            }
+            for &(x, y) in &removal.cells {
+                if naive {
+                    removed[y] |= 1 << x;
+                }
+                else {
+                    self.set((x, y), Id::None);
+                }
+            }
+
+            // Compact the open cells of each column from the bottom upwards
+            for x in 0..self.width {
+                let open = (0..self.height).rev()
+                                           .filter(|&y| self.mask((x, y)) == Mask::Open)
+                                           .collect::<Vec<_>>();
+
+                let mut next = 0;
+                for &y in &open {
+                    if removed[y] & (1 << x) == 0 {
+                        let (id, time) = (self.data[y][x], self.locked[y][x]);
+                        self.put((x, open[next]), id, time);
+                        next += 1;
+                    }
+                }
+
+                for &y in &open[next..] {
+                    self.set((x, y), Id::None);
+                }
+            }
+
+            return;
+        }
+
+        for &p in &removal.cells {
+            self.set(p, Id::None);
+        }
+
+        // Move each remaining row down to the next free row, working from the
+        // bottom of the field upwards.
+        let mut next = self.height;
+        for y in (0..self.height).rev() {
+            if !removal.rows.contains(&y) {
+                next -= 1;
+                self.rows[next] = self.rows[y];
+                self.data.swap(next, y);
+                self.locked.swap(next, y);
+            }
+        }
+
+        // Any remaining rows at the top are now empty
+        for y in 0..next {
+            self.rows[y] = 0;
+            for cell in &mut self.data[y] {
+                *cell = Id::None;
+            }
+            for time in &mut self.locked[y] {
+                *time = 0;
+            }
+        }
+    }
+
+    /// Drop every connected group of cells as far as it will fall.
+    ///
+    /// If `sticky` is set, only cells with the same `Id` are connected.
+    fn settle(&mut self, sticky: bool) {
+        loop {
+            let mut groups = self.groups(sticky);
+
+            // Drop the lowest groups first so those above can land on them
+            groups.sort_by_key(|g| g.iter().map(|&(_, y)| y).max());
+
+            let mut moved = false;
+            for group in groups.iter().rev() {
+                let cells = group.iter()
+                                 .map(|&(x, y)| (self.data[y][x], self.locked[y][x]))
+                                 .collect::<Vec<_>>();
+                for &p in group {
+                    self.set(p, Id::None);
+                }
+
+                let mut dy = 0;
+                while group.iter().all(|&(x, y)| !self.collides((i32!(x), i32!(y + dy + 1)))) {
+                    dy += 1;
+                }
+
+                for (&(x, y), &(id, time)) in group.iter().zip(&cells) {
+                    self.put((x, y + dy), id, time);
+                }
+
+                moved |= dy != 0;
+            }
+
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    /// Return each 4-connected group of occupied cells.
+    ///
+    /// If `sticky` is set, only cells with the same `Id` are connected.
+    fn groups(&self, sticky: bool) -> Vec<Vec<(usize, usize)>> {
+        let mut seen = vec![0u64; self.height];
+        let mut groups = Vec::new();
+
+        for (y, x) in iproduct!(0..self.height, 0..self.width) {
+            if !self.occupies((x, y)) || seen[y] & (1 << x) != 0 {
+                continue;
+            }
+
+            let mut group = Vec::new();
+            let mut stack = vec![(x, y)];
+            seen[y] |= 1 << x;
+
+            while let Some((a, b)) = stack.pop() {
+                group.push((a, b));
+
+                let mut adjacent = vec![(a + 1, b), (a, b + 1)];
+                if a > 0 {
+                    adjacent.push((a - 1, b));
+                }
+                if b > 0 {
+                    adjacent.push((a, b - 1));
+                }
+
+                for (c, d) in adjacent {
+                    if self.occupies((c, d)) && seen[d] & (1 << c) == 0 &&
+                            (!sticky || self.get((c, d)) == self.get((a, b))) {
+                        seen[d] |= 1 << c;
+                        stack.push((c, d));
+                    }
+                }
+            }
+
+            groups.push(group);
+        }
+
+        groups
     }
 
     /// Freeze a block into place on the field. This takes ownership of the
@@ -158,8 +635,43 @@ impl Field {
     /// // block.shift(Direction::Right); // Compile Error
     /// ```
     pub fn freeze(&mut self, block: Block) {
-        for &(x, y) in block.rs.data(block.id, block.r) {
-            self.data[usize!(block.y + i32!(y))][usize!(block.x + i32!(x))] = block.id;
+        for (x, y) in block.cells() {
+            self.set((usize!(x), usize!(y)), block.id);
+        }
+    }
+
+    /// Set the value at the specified field location.
+    ///
+    /// Setting a value of `Id::None` empties the cell.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// field.set((5, 10), block::Id::T);
+    ///
+    /// assert!(field.occupies((5, 10)));
+    /// ```
+    pub fn set(&mut self, (x, y): (usize, usize), id: Id) {
+        let time = self.time;
+        self.put((x, y), id, time);
+    }
+
+    /// Set the value at the specified field location with a specific time.
+    ///
+    /// This is used to move cells without altering when they were locked.
+    fn put(&mut self, (x, y): (usize, usize), id: Id, time: u64) {
+        assert!(x < self.width && y < self.height);
+        assert!(id == Id::None || self.mask((x, y)) == Mask::Open, "cannot fill a masked cell");
+        self.data[y][x] = id;
+        self.locked[y][x] = time;
+
+        if id == Id::None {
+            self.rows[y] &= !(1 << x);
+        }
+        else {
+            self.rows[y] |= 1 << x;
         }
     }
 
@@ -180,20 +692,567 @@ impl Field {
         self.data[y][x]
     }
 
+    /// Return the time at which the specified location was filled.
+    ///
+    /// `None` is returned if the location is empty.
+    pub fn locked_at(&self, (x, y): (usize, usize)) -> Option<u64> {
+        if self.occupies((x, y)) { Some(self.locked[y][x]) } else { None }
+    }
+
+    /// Return how the specified location should be displayed under the
+    /// current `Visibility`.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::with_options(FieldOptions {
+    ///     visibility: Visibility::Fading(60), ..Default::default()
+    /// });
+    ///
+    /// field.set((0, 24), block::Id::T);
+    /// assert_eq!(field.appearance((0, 24)), Appearance::Colour(block::Id::T));
+    ///
+    /// field.time = 60;
+    /// assert_eq!(field.appearance((0, 24)), Appearance::Hidden);
+    /// ```
+    pub fn appearance(&self, (x, y): (usize, usize)) -> Appearance {
+        let locked = match self.locked_at((x, y)) {
+            Some(locked) => locked,
+            None => return Appearance::Empty
+        };
+
+        let visible = match self.visibility {
+            Visibility::Visible | Visibility::Monochrome => true,
+            Visibility::Invisible => false,
+            Visibility::Fading(ticks) => self.time.saturating_sub(locked) < ticks,
+            Visibility::HiddenUntilClear => self.last_clear.is_some_and(|t| locked <= t)
+        };
+
+        match (visible, self.visibility) {
+            (false, _) => Appearance::Hidden,
+            (true, Visibility::Monochrome) => Appearance::Plain,
+            (true, _) => Appearance::Colour(self.data[y][x])
+        }
+    }
+
     /// Return true if the value at the specified location is non-empty.
     ///
     /// This is a convenience function which queries `at` and checks if the
     /// result is of empty `Id`.
     ///
-    /// If the input values are outside the range of the field then true is
-    /// returned. Explicit bounds checking is left up the user.
+    /// If the input values are outside the range of the field then false is
+    /// returned. Use `collides` if positions outside the field should count
+    /// as occupied.
+    ///
+    /// Masked cells are never occupied. Use `collides` to test whether a
+    /// block can enter a cell.
     pub fn occupies(&self, (x, y): (usize, usize)) -> bool {
         if x >= self.width || y >= self.height {
             false
         }
         else {
-            self.data[y][x] != Id::None
+            self.rows[y] & (1 << x) != 0
         }
         //assert!(x < self.width && y < self.height);
     }
+
+    /// Return true if the specified location is outside the field, is
+    /// occupied or is masked.
+    ///
+    /// This is the test used for `Block` collisions, and accepts signed
+    /// coordinates so offset calculations do not need to be checked.
+    ///
+    /// Cells of any blocks set by `set_active` also collide.
+    pub fn collides(&self, (x, y): (i32, i32)) -> bool {
+        if x < 0 || y < 0 || x >= i32!(self.width) || y >= i32!(self.height) {
+            return true;
+        }
+
+        let y = usize!(y);
+        (self.rows[y] | self.blocked[y] | self.void[y] | self.active[y]) & (1 << x) != 0
+    }
+
+    /// Set the blocks which are active on this field alongside the block
+    /// being moved.
+    ///
+    /// The cells of these blocks collide, but are otherwise not part of the
+    /// field. This allows multiple players to share a field. Any previously
+    /// set blocks are replaced.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// let other = Block::new(block::Id::O, &field);
+    /// field.set_active(&[other]);
+    ///
+    /// assert!(field.collides((5, 1)));
+    /// assert!(!field.occupies((5, 1)));
+    /// ```
+    pub fn set_active(&mut self, blocks: &[Block]) {
+        for row in &mut self.active {
+            *row = 0;
+        }
+
+        for block in blocks {
+            for (x, y) in block.cells() {
+                if x >= 0 && y >= 0 && x < i32!(self.width) && y < i32!(self.height) {
+                    self.active[usize!(y)] |= 1 << x;
+                }
+            }
+        }
+    }
+
+    /// Return the mask of the specified field location.
+    pub fn mask(&self, (x, y): (usize, usize)) -> Mask {
+        assert!(x < self.width && y < self.height);
+
+        if self.blocked[y] & (1 << x) != 0 {
+            Mask::Blocked
+        }
+        else if self.void[y] & (1 << x) != 0 {
+            Mask::Void
+        }
+        else {
+            Mask::Open
+        }
+    }
+
+    /// Set the mask of the specified field location.
+    ///
+    /// Any block occupying the cell is removed.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// field.set_mask((0, 24), Mask::Void);
+    ///
+    /// // The bottom row no longer needs its first cell to be filled
+    /// for x in 1..field.width {
+    ///     field.set((x, 24), block::Id::I);
+    /// }
+    /// assert!(field.is_full(24));
+    /// ```
+    pub fn set_mask(&mut self, (x, y): (usize, usize), mask: Mask) {
+        self.set((x, y), Id::None);
+
+        self.blocked[y] &= !(1 << x);
+        self.void[y] &= !(1 << x);
+
+        match mask {
+            Mask::Open => (),
+            Mask::Blocked => self.blocked[y] |= 1 << x,
+            Mask::Void => self.void[y] |= 1 << x
+        }
+
+        self.masked = self.blocked.iter().chain(&self.void).any(|&m| m != 0);
+    }
+
+    /// Return the occupancy bitmask of each row.
+    ///
+    /// Bit `x` of row `y` is set if the cell `(x, y)` is occupied.
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    /// Return the `Id` of each cell, indexed as `data()[y][x]`.
+    ///
+    /// Cells are modified with `set` so the row bitmasks stay in sync.
+    pub fn data(&self) -> &[Vec<Id>] {
+        &self.data
+    }
+
+    /// Return the height of the specified column.
+    ///
+    /// This is measured from the bottom of the field, so an empty column has
+    /// a height of 0.
+    pub fn column_height(&self, x: usize) -> usize {
+        (0..self.height).find(|&y| self.rows[y] & (1 << x) != 0)
+                        .map_or(0, |y| self.height - y)
+    }
+
+    /// Return true if a downward pointing T-block centred at `(x, y)` would
+    /// fit, is supported, and at least three of its corners are filled.
+    ///
+    /// Cells outside the field and masked cells are considered filled.
+    pub fn is_t_slot(&self, (x, y): (usize, usize)) -> bool {
+        if x == 0 || y == 0 {
+            return false;
+        }
+
+        let filled = |(x, y): (usize, usize)| self.collides((i32!(x), i32!(y)));
+
+        let corners = [(x - 1, y - 1), (x + 1, y - 1), (x - 1, y + 1), (x + 1, y + 1)];
+
+        !filled((x - 1, y)) && !filled((x, y)) && !filled((x + 1, y)) &&
+            !filled((x, y + 1)) && filled((x, y + 2)) &&
+            corners.iter().filter(|&&c| filled(c)).count() >= 3
+    }
+
+    /// Compute the analysis metrics of this field.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    ///
+    /// let mut field = Field::new();
+    /// field.set((0, 23), block::Id::I);
+    ///
+    /// let metrics = field.metrics();
+    /// assert_eq!(metrics.heights[0], 2);
+    /// assert_eq!(metrics.holes, 1);
+    /// assert_eq!(metrics.covered, 1);
+    /// ```
+    pub fn metrics(&self) -> FieldMetrics {
+        let full = self.full_row();
+
+        // Walk down the field, tracking which columns have been covered so
+        // far. Any empty cell in a covered column is a hole.
+        let mut heights = vec![0; self.width];
+        let mut above = 0;
+        let mut holes = Vec::with_capacity(self.height);
+        let mut row_transitions = 0;
+        let mut column_transitions = 0;
+
+        for y in 0..self.height {
+            let row = self.rows[y];
+
+            let mut top = row & !above;
+            while top != 0 {
+                heights[top.trailing_zeros() as usize] = self.height - y;
+                top &= top - 1;
+            }
+
+            holes.push(!(row | self.blocked[y] | self.void[y]) & above & full);
+            above |= row;
+
+            if row != 0 {
+                // Surround the row with filled walls on both sides. This needs
+                // two more bits than a row, so is done in a u128.
+                let walled = (u128::from(row) << 1) | 1 | (1 << (self.width + 1));
+                let edges = (u128::from(full) << 1) | 1;
+                row_transitions += ((walled ^ (walled >> 1)) & edges).count_ones();
+            }
+
+            let below = if y + 1 < self.height { self.rows[y + 1] } else { full };
+            column_transitions += (row ^ below).count_ones();
+        }
+
+        // Walk back up, counting the occupied cells above any hole
+        let mut covered = 0;
+        let mut below = 0;
+        for (row, hole) in self.rows.iter().zip(&holes).rev() {
+            covered += (row & below).count_ones();
+            below |= hole;
+        }
+
+        let bumpiness = heights.windows(2)
+                               .map(|w| cmp::max(w[0], w[1]) - cmp::min(w[0], w[1]))
+                               .sum();
+
+        let wells = (0..self.width).map(|x| {
+            let left = if x == 0 { self.height } else { heights[x - 1] };
+            let right = if x + 1 == self.width { self.height } else { heights[x + 1] };
+            cmp::min(left, right).saturating_sub(heights[x])
+        }).collect::<Vec<_>>();
+
+        let deepest_well = (0..self.width).filter(|&x| wells[x] != 0)
+                                          .max_by_key(|&x| wells[x]);
+
+        let t_slots = iproduct!(1..self.height.saturating_sub(1), 1..self.width.saturating_sub(1))
+                          .map(|(y, x)| (x, y))
+                          .filter(|&p| self.is_t_slot(p))
+                          .collect();
+
+        FieldMetrics {
+            aggregate_height: heights.iter().sum(),
+            holes: holes.iter().map(|h| h.count_ones() as usize).sum(),
+            covered: covered as usize,
+            bumpiness,
+            row_transitions: row_transitions as usize,
+            column_transitions: column_transitions as usize,
+            heights,
+            wells,
+            deepest_well,
+            t_slots
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_lines() {
+        let mut field = Field::new();
+
+        for x in 0..field.width {
+            field.set((x, 24), Id::I);
+            field.set((x, 22), Id::O);
+        }
+        field.set((0, 23), Id::T);
+        field.set((1, 21), Id::S);
+
+        assert_eq!(field.clear_lines(), 2);
+
+        assert_eq!(field.get((0, 24)), Id::T);
+        assert_eq!(field.get((1, 23)), Id::S);
+        assert_eq!(field.rows()[24], 0b01);
+        assert_eq!(field.rows()[23], 0b10);
+        assert!((0..23).all(|y| field.rows()[y] == 0));
+        assert!((0..field.width).all(|x| field.get((x, 22)) == Id::None));
+    }
+
+    #[test]
+    fn test_collides() {
+        let mut field = Field::new();
+        field.set((3, 4), Id::I);
+
+        assert!(field.collides((3, 4)));
+        assert!(field.collides((-1, 4)));
+        assert!(field.collides((10, 4)));
+        assert!(field.collides((3, 25)));
+        assert!(!field.collides((4, 4)));
+
+        field.set((3, 4), Id::None);
+        assert!(!field.collides((3, 4)));
+    }
+
+    #[test]
+    fn test_data() {
+        let mut field = Field::new();
+        field.set((1, field.height - 1), Id::T);
+
+        assert_eq!(field.data().len(), field.height);
+        assert_eq!(field.data()[field.height - 1][1], Id::T);
+        assert_eq!(field.data()[field.height - 1][0], Id::None);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut field = Field::new();
+
+        // |    ...   |
+        // |#  #      |
+        // |# ##    # |
+        // |## #  ### |
+        for &(x, y) in [(0, 22), (3, 22), (0, 23), (2, 23), (3, 23), (8, 23),
+                        (0, 24), (1, 24), (3, 24), (6, 24), (7, 24), (8, 24)].iter() {
+            field.set((x, y), Id::I);
+        }
+
+        let metrics = field.metrics();
+        assert_eq!(metrics.heights, vec![3, 1, 2, 3, 0, 0, 1, 1, 2, 0]);
+        assert_eq!(metrics.aggregate_height, 13);
+        assert_eq!(metrics.holes, 1);
+        assert_eq!(metrics.covered, 1);
+        assert_eq!(metrics.bumpiness, 11);
+        assert_eq!(metrics.wells, vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(metrics.deepest_well, Some(9));
+        assert_eq!(metrics.row_transitions, 4 + 6 + 6);
+        assert_eq!(metrics.column_transitions, 1 + 1 + 3 + 1 + 1 + 1 + 1 + 1 + 1 + 1);
+        assert!(metrics.t_slots.is_empty());
+
+        let empty = Field::new().metrics();
+        assert_eq!(empty.aggregate_height, 0);
+        assert_eq!(empty.row_transitions, 0);
+        assert_eq!(empty.column_transitions, 10);
+        assert_eq!(empty.deepest_well, None);
+    }
+
+    #[test]
+    fn test_metrics_wide() {
+        for &width in [63, 64].iter() {
+            let mut field = Field::with_options(FieldOptions { width, ..Default::default() });
+            for x in 1..width {
+                field.set((x, field.height - 1), Id::I);
+            }
+
+            let metrics = field.metrics();
+            assert_eq!(metrics.row_transitions, 2);
+            assert_eq!(metrics.aggregate_height, width - 1);
+            assert_eq!(metrics.wells[0], 1);
+        }
+    }
+
+    #[test]
+    fn test_t_slot() {
+        let mut field = Field::new();
+
+        // |   #      |
+        // |#   ######|
+        // |## #######|
+        field.set((3, 22), Id::I);
+        for x in 0..field.width {
+            if x != 2 {
+                field.set((x, 24), Id::I);
+            }
+            if x == 0 || x > 3 {
+                field.set((x, 23), Id::I);
+            }
+        }
+
+        assert!(field.is_t_slot((2, 23)));
+        assert_eq!(field.metrics().t_slots, vec![(2, 23)]);
+    }
+
+    #[test]
+    fn test_clear_with_degenerate() {
+        let mut field = Field::with_options(FieldOptions {
+            gravity: Gravity::Cascade, ..Default::default()
+        });
+        field.set((0, 24), Id::I);
+
+        // Removing empty cells does nothing and must not loop forever
+        let clear = field.clear_with(|_| Removal { rows: vec![0], cells: vec![(1, 24)] });
+        assert_eq!(clear.lines, 0);
+        assert_eq!(clear.chain, 0);
+
+        // Duplicate rows are only counted once
+        let clear = field.clear_with(|_| Removal { rows: vec![24, 24], cells: Vec::new() });
+        assert_eq!(clear.lines, 1);
+        assert_eq!(clear.chain, 1);
+        assert!(!field.occupies((0, 24)));
+    }
+
+    #[test]
+    fn test_clear_sticky() {
+        // |   JTT    |
+        // |OOOO  OOOO|
+        // |IIIIIIIIII|
+        let setup = |gravity| {
+            let mut field = Field::with_options(FieldOptions {
+                gravity, ..Default::default()
+            });
+
+            for x in 0..field.width {
+                field.set((x, 24), Id::I);
+                if !(4..=5).contains(&x) {
+                    field.set((x, 23), Id::O);
+                }
+            }
+            field.set((3, 22), Id::J);
+            field.set((4, 22), Id::T);
+            field.set((5, 22), Id::T);
+            field
+        };
+
+        // The T cells fall separately from the J and fill the gap
+        let mut field = setup(Gravity::Sticky);
+        let clear = field.clear();
+        assert_eq!((clear.lines, clear.chain), (2, 2));
+        assert_eq!(field.rows()[24], 0b1000);
+        assert_eq!(field.get((3, 24)), Id::J);
+
+        // The T cells are held up by the J
+        let mut field = setup(Gravity::Cascade);
+        let clear = field.clear();
+        assert_eq!((clear.lines, clear.chain), (1, 1));
+        assert_eq!(field.get((4, 23)), Id::T);
+    }
+
+    #[test]
+    fn test_clear_cascade() {
+        let mut field = Field::with_options(FieldOptions {
+            gravity: Gravity::Cascade, ..Default::default()
+        });
+
+        // |#         |
+        // |          |
+        // | #########|
+        // |##########|
+        for x in 0..field.width {
+            field.set((x, 24), Id::I);
+            if x > 0 {
+                field.set((x, 23), Id::L);
+            }
+        }
+        field.set((0, 21), Id::T);
+
+        let clear = field.clear();
+        assert_eq!((clear.lines, clear.chain), (2, 2));
+        assert!(field.rows().iter().all(|&r| r == 0));
+
+        // Naive gravity leaves the cell floating
+        let mut field = Field::new();
+        for x in 0..field.width {
+            field.set((x, 24), Id::I);
+        }
+        field.set((0, 21), Id::T);
+
+        let clear = field.clear();
+        assert_eq!((clear.lines, clear.chain), (1, 1));
+        assert_eq!(field.get((0, 22)), Id::T);
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut field = Field::new();
+
+        // |  #       |
+        // |  =       |
+        // |#########.|
+        field.set((2, 22), Id::T);
+        field.set_mask((2, 23), Mask::Blocked);
+        field.set_mask((9, 24), Mask::Void);
+        for x in 0..9 {
+            field.set((x, 24), Id::I);
+        }
+
+        assert!(field.collides((2, 23)));
+        assert!(field.collides((9, 24)));
+        assert!(!field.occupies((2, 23)));
+        assert!(field.is_full(24));
+        assert!(!field.is_full(23));
+
+        assert_eq!(field.clear_lines(), 1);
+
+        // The blocked cell stays in place, and the cell above falls past it
+        assert_eq!(field.mask((2, 23)), Mask::Blocked);
+        assert_eq!(field.mask((9, 24)), Mask::Void);
+        assert_eq!(field.get((2, 24)), Id::T);
+        assert_eq!(field.rows()[24], 0b100);
+        assert!((0..24).all(|y| field.rows()[y] == 0));
+
+        // A row without any open cells is never cleared
+        for x in 0..field.width {
+            field.set_mask((x, 0), Mask::Void);
+        }
+        assert!(!field.is_full(0));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let mut field = Field::with_options(FieldOptions {
+            visibility: Visibility::HiddenUntilClear, ..Default::default()
+        });
+
+        field.time = 5;
+        field.set((0, 23), Id::T);
+        field.time = 10;
+        for x in 0..field.width {
+            field.set((x, 24), Id::I);
+        }
+
+        assert_eq!(field.appearance((0, 23)), Appearance::Hidden);
+        assert_eq!(field.appearance((1, 23)), Appearance::Empty);
+
+        // The cell keeps its lock time when moving down
+        field.time = 20;
+        field.clear_lines();
+        assert_eq!(field.locked_at((0, 24)), Some(5));
+        assert_eq!(field.appearance((0, 24)), Appearance::Colour(Id::T));
+
+        field.visibility = Visibility::Fading(15);
+        assert_eq!(field.appearance((0, 24)), Appearance::Hidden);
+        field.visibility = Visibility::Fading(16);
+        assert_eq!(field.appearance((0, 24)), Appearance::Colour(Id::T));
+        field.visibility = Visibility::Monochrome;
+        assert_eq!(field.appearance((0, 24)), Appearance::Plain);
+    }
 }
@@ -0,0 +1,96 @@
+//! Finesse analysis of block placements.
+//!
+//! Finesse is the minimum number of inputs required to move a block from its
+//! spawn position to a final resting position. A held movement (DAS) counts
+//! as a single input, and the final hard drop is not counted.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::finesse;
+//!
+//! let field = Field::new();
+//! let wk = wallkick::new("srs").unwrap();
+//!
+//! let spawn = Block::new(block::Id::T, &field);
+//! let mut target = spawn.clone();
+//! target.shift_extend(&field, Direction::Left);
+//! target.shift_extend(&field, Direction::Down);
+//!
+//! // A single DAS left is required
+//! assert_eq!(finesse::minimum(&field, &spawn, &target, &*wk), Some(1));
+//! ```
+
+use block::Block;
+use field::Field;
+use movegen::{self, Input};
+use wallkick::Wallkick;
+
+/// The inputs considered when calculating finesse.
+///
+/// Soft drop is not included, so tucks and spins have no finesse value.
+pub static INPUTS: [Input; 6] = [
+    Input::Left, Input::Right, Input::DasLeft, Input::DasRight,
+    Input::RotateLeft, Input::RotateRight
+];
+
+/// Return the minimum number of inputs required to move `spawn` to the
+/// resting position of `target`.
+///
+/// `None` is returned if the target cannot be reached without a soft drop.
+pub fn minimum(field: &Field, spawn: &Block, target: &Block, wallkick: &Wallkick) -> Option<usize> {
+    let cells = target.ghost(field).cells();
+
+    movegen::search(field, spawn, wallkick, &INPUTS).into_iter()
+        .find(|m| m.block.cells() == cells)
+        .map(|m| m.inputs.len())
+}
+
+/// Return the number of inputs used in excess of the minimum.
+pub fn faults(minimum: usize, actual: usize) -> usize {
+    actual.saturating_sub(minimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use import::*;
+
+    #[test]
+    fn test_minimum() {
+        let field = Field::new();
+        let wk = wallkick::new("srs").unwrap();
+
+        // Every position of every piece on an empty field is reachable with
+        // at most 4 inputs in SRS.
+        for &id in block::Id::variants() {
+            let spawn = Block::new(id, &field);
+
+            for m in movegen::generate(&field, &spawn, &*wk) {
+                let inputs = minimum(&field, &spawn, &m.block, &*wk).unwrap();
+                assert!(inputs <= 4, "{:?} {:?} {}", id, m.block.cells(), inputs);
+            }
+
+            assert_eq!(minimum(&field, &spawn, &spawn, &*wk), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_tuck() {
+        let mut field = Field::new();
+        for x in 0..9 {
+            field.set((x, 20), block::Id::I);
+        }
+
+        let wk = wallkick::new("srs").unwrap();
+        let spawn = Block::new(block::Id::O, &field);
+
+        // An O block tucked underneath the overhang
+        let target = Block::with_options(block::Id::O, &field, BlockOptions {
+            x: Some(6), y: Some(23), ..Default::default()
+        });
+
+        assert_eq!(minimum(&field, &spawn, &target, &*wk), None);
+    }
+}
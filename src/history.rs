@@ -67,9 +67,19 @@ impl History {
             }
         }
 
+        self.snapshot = controller.active;
         self.tick_count += 1;
     }
 
+    /// Return how many times `action` has been pressed since the specified
+    /// tick (inclusive).
+    pub fn presses_since(&self, tick: u64, action: Action) -> usize {
+        self.history.iter().rev()
+            .take_while(|e| e.ticks >= tick)
+            .filter(|e| e.press && e.action == action)
+            .count()
+    }
+
     /// Return the current event sequence
     pub fn get_sequence(&self) -> &[Event] {
         &self.history
@@ -0,0 +1,209 @@
+//! The hold system.
+//!
+//! Holding sets the active piece aside to be played later. How this happens
+//! is decided by a `HoldMode`:
+//!
+//!  - `Disabled`: pieces cannot be held.
+//!  - `Swap`: the active piece is swapped with the held piece. If nothing is
+//!    held, the next piece is taken from the randomizer.
+//!  - `Queue`: held pieces form a queue of `slots` pieces. Holding adds the
+//!    active piece to the back of the queue, and once the queue is full the
+//!    piece at the front becomes active.
+//!  - `Preview`: the held piece takes the first preview slot (as in TGM3), so
+//!    it is the next piece spawned if it is not swapped back first.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::hold::{Hold, HoldMode, HoldOptions};
+//!
+//! let mut hold = Hold::new(HoldOptions { mode: HoldMode::Queue, slots: 2, ..Default::default() });
+//!
+//! // Nothing is taken from the hold until both slots are filled
+//! assert_eq!(hold.push(block::Id::I, Rotation::R0), None);
+//! assert_eq!(hold.push(block::Id::T, Rotation::R0), None);
+//! assert_eq!(hold.push(block::Id::O, Rotation::R0), Some((block::Id::I, Rotation::R0)));
+//! assert_eq!(hold.held(), vec![block::Id::T, block::Id::O]);
+//! ```
+
+use std::collections::VecDeque;
+
+use block::{Id, Rotation};
+
+/// How pieces are held.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum HoldMode {
+    /// Pieces cannot be held
+    Disabled,
+
+    /// The active piece is swapped with the held piece
+    Swap,
+
+    /// Held pieces are queued, see `HoldOptions::slots`
+    Queue,
+
+    /// The held piece takes the first preview slot
+    Preview
+}
+
+/// Options which configure a `Hold`.
+///
+/// The default values are:
+///
+/// ```text
+/// HoldOptions {
+///     mode: HoldMode::Swap,
+///     slots: 1,
+///     reset_rotation: true
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HoldOptions {
+    /// How pieces are held
+    pub mode: HoldMode,
+
+    /// How many pieces can be held with `HoldMode::Queue`
+    pub slots: usize,
+
+    /// Does a piece taken from the hold spawn in its spawn rotation? If not,
+    /// it keeps the rotation it was held in.
+    pub reset_rotation: bool
+}
+
+impl Default for HoldOptions {
+    fn default() -> HoldOptions {
+        HoldOptions { mode: HoldMode::Swap, slots: 1, reset_rotation: true }
+    }
+}
+
+/// The held pieces of a player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hold {
+    /// Options used by this hold
+    pub op: HoldOptions,
+
+    /// The held pieces and their rotations, oldest first
+    pieces: VecDeque<(Id, Rotation)>
+}
+
+impl Default for Hold {
+    fn default() -> Hold {
+        Hold::new(HoldOptions { ..Default::default() })
+    }
+}
+
+impl Hold {
+    /// Construct a new empty `Hold` with the specified options.
+    pub fn new(options: HoldOptions) -> Hold {
+        Hold { op: options, pieces: VecDeque::new() }
+    }
+
+    /// Returns true if pieces can be held.
+    pub fn enabled(&self) -> bool {
+        self.op.mode != HoldMode::Disabled && self.slots() > 0
+    }
+
+    /// Return the number of pieces which can be held.
+    pub fn slots(&self) -> usize {
+        match self.op.mode {
+            HoldMode::Disabled => 0,
+            HoldMode::Queue => self.op.slots,
+            HoldMode::Swap | HoldMode::Preview => 1
+        }
+    }
+
+    /// Return the pieces shown as held, oldest first.
+    ///
+    /// With `HoldMode::Preview` the held piece is part of the preview instead,
+    /// see `queued`.
+    pub fn held(&self) -> Vec<Id> {
+        match self.op.mode {
+            HoldMode::Preview => Vec::new(),
+            _ => self.pieces.iter().map(|&(id, _)| id).collect()
+        }
+    }
+
+    /// Return the held pieces which are spawned before any piece from the
+    /// randomizer.
+    pub fn queued(&self) -> Vec<Id> {
+        match self.op.mode {
+            HoldMode::Preview => self.pieces.iter().map(|&(id, _)| id).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    /// Return the piece a hold would make active, or `None` if it would be
+    /// taken from the randomizer.
+    pub fn next(&self) -> Option<Id> {
+        if self.enabled() && self.pieces.len() >= self.slots() {
+            self.pieces.front().map(|&(id, _)| id)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Hold the active piece `id` with rotation `r`.
+    ///
+    /// The piece and rotation which should become active is returned, or
+    /// `None` if the next piece should be taken from the randomizer. The
+    /// returned rotation is `R0` if rotations are reset.
+    pub fn push(&mut self, id: Id, r: Rotation) -> Option<(Id, Rotation)> {
+        if !self.enabled() {
+            return Some((id, r));
+        }
+
+        let r = if self.op.reset_rotation { Rotation::R0 } else { r };
+        self.pieces.push_back((id, r));
+
+        if self.pieces.len() > self.slots() {
+            self.pieces.pop_front()
+        }
+        else {
+            None
+        }
+    }
+
+    /// Take the next queued piece and its rotation, if any.
+    ///
+    /// This is used to spawn pieces with `HoldMode::Preview`, where the held
+    /// piece is spawned before the randomizer is used.
+    pub fn pop_queued(&mut self) -> Option<(Id, Rotation)> {
+        match self.op.mode {
+            HoldMode::Preview => self.pieces.pop_front(),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes() {
+        let mut hold = Hold::new(HoldOptions { mode: HoldMode::Disabled, ..Default::default() });
+        assert!(!hold.enabled());
+        assert_eq!(hold.push(Id::I, Rotation::R90), Some((Id::I, Rotation::R90)));
+
+        let mut hold = Hold::default();
+        assert_eq!(hold.push(Id::I, Rotation::R90), None);
+        assert_eq!(hold.next(), Some(Id::I));
+        assert_eq!(hold.push(Id::T, Rotation::R0), Some((Id::I, Rotation::R0)));
+        assert_eq!(hold.held(), vec![Id::T]);
+        assert_eq!(hold.pop_queued(), None);
+
+        let mut hold = Hold::new(HoldOptions { reset_rotation: false, ..Default::default() });
+        hold.push(Id::I, Rotation::R90);
+        assert_eq!(hold.push(Id::T, Rotation::R0), Some((Id::I, Rotation::R90)));
+
+        let mut hold = Hold::new(HoldOptions { mode: HoldMode::Preview, ..Default::default() });
+        hold.push(Id::S, Rotation::R0);
+        assert!(hold.held().is_empty());
+        assert_eq!(hold.queued(), vec![Id::S]);
+        assert_eq!(hold.pop_queued(), Some((Id::S, Rotation::R0)));
+        assert_eq!(hold.next(), None);
+    }
+}
@@ -20,10 +20,14 @@
 //! use tetrs::field::Field;
 //! ```
 
-pub use engine::{Engine, EngineOptions, EngineSettings};
+pub use engine::{Engine, EngineOptions, EngineSettings, Placement, GameEvent};
 pub use block::{self, Block, BlockOptions, Rotation, Direction};
-pub use field::{Field, FieldOptions};
+pub use field::{Field, FieldOptions, FieldMetrics, Gravity, Clear, Mask, SpawnRegion, Visibility,
+                Appearance};
 pub use controller;
+pub use piece_set::{self, PieceSet};
+pub use hold::{self, Hold, HoldOptions};
 pub use randomizer::{self, Randomizer};
 pub use wallkick::{self, Wallkick};
+pub use clear_rule::{self, ClearRule};
 pub use rotation_system::{self, RotationSystem};
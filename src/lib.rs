@@ -57,8 +57,10 @@ pub mod schema;
 
 pub mod field;
 pub mod block;
+pub mod piece_set;
 pub mod controller;
 pub mod wallkick;
+pub mod clear_rule;
 pub mod randomizer;
 pub mod rotation_system;
 pub mod engine;
@@ -66,3 +68,8 @@ pub mod utility;
 pub mod statistics;
 pub mod import;
 pub mod history;
+pub mod hold;
+pub mod movegen;
+pub mod bot;
+pub mod finesse;
+pub mod perfect_clear;
@@ -0,0 +1,246 @@
+//! Generates all reachable placements of a block on a field.
+//!
+//! Move generation performs a search over every position a `Block` can reach
+//! using shifts, rotations (with wallkicks) and soft drops. Each distinct
+//! resting position is returned alongside the shortest input sequence which
+//! reaches it.
+//!
+//! This is the basis for bots, finesse checking and puzzle solvers. Rotations
+//! are performed with `rotate_with_wallkick`, so the positions found are
+//! exactly those reachable within an `Engine`.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::movegen;
+//!
+//! let field = Field::new();
+//! let block = Block::new(block::Id::T, &field);
+//! let wk = wallkick::new("srs").unwrap();
+//!
+//! // Every resting position of a T-block on an empty field
+//! let moves = movegen::generate(&field, &block, &*wk);
+//! assert_eq!(moves.iter().filter(|m| !m.spin).count(), 34);
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+
+use block::{Block, Rotation, Direction};
+use field::Field;
+use wallkick::Wallkick;
+use utility::BlockHelper;
+
+/// A single input which can be applied to a block during a search.
+#[derive(Hash, Clone, Debug, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Input {
+    Left, Right, RotateLeft, RotateRight,
+
+    /// Move the block down until it rests on the field.
+    SoftDrop,
+
+    /// Move the block left until it hits a wall or the stack.
+    DasLeft,
+
+    /// Move the block right until it hits a wall or the stack.
+    DasRight
+}
+
+impl Input {
+    /// Return all known `Input` variants.
+    pub fn variants() -> Vec<Input> {
+        vec![Input::Left, Input::Right, Input::RotateLeft, Input::RotateRight,
+             Input::SoftDrop, Input::DasLeft, Input::DasRight]
+    }
+
+    /// Apply this input to `block`, returning whether the block moved.
+    pub fn apply(&self, block: &mut Block, field: &Field, wallkick: &Wallkick) -> bool {
+        match *self {
+            Input::Left => block.shift(field, Direction::Left),
+            Input::Right => block.shift(field, Direction::Right),
+            Input::RotateLeft => block.rotate_with_wallkick(field, wallkick, Rotation::R270),
+            Input::RotateRight => block.rotate_with_wallkick(field, wallkick, Rotation::R90),
+            Input::SoftDrop => Input::extend(block, field, Direction::Down),
+            Input::DasLeft => Input::extend(block, field, Direction::Left),
+            Input::DasRight => Input::extend(block, field, Direction::Right)
+        }
+    }
+
+    /// Shift a block as far as possible, returning whether it moved.
+    fn extend(block: &mut Block, field: &Field, direction: Direction) -> bool {
+        let moved = block.shift(field, direction);
+        block.shift_extend(field, direction);
+        moved
+    }
+
+    /// Is this input a rotation?
+    pub fn is_rotation(&self) -> bool {
+        *self == Input::RotateLeft || *self == Input::RotateRight
+    }
+}
+
+/// A reachable resting position of a block.
+#[derive(Clone)]
+pub struct Move {
+    /// The block at its final resting position.
+    pub block: Block,
+
+    /// The shortest sequence of inputs which reaches this position.
+    ///
+    /// A hard drop is implied after the final input.
+    pub inputs: Vec<Input>,
+
+    /// Was the block rotated into its resting position?
+    ///
+    /// This is the case when the final input of the shortest path is a
+    /// rotation and no further downward movement occurs.
+    pub spin: bool
+}
+
+impl Move {
+    /// Return the highest row occupied by this move.
+    pub fn top(&self) -> i32 {
+        self.block.cells().iter().map(|&(_, y)| y).min().unwrap()
+    }
+
+    /// Return the cells of this move relative to its highest row.
+    ///
+    /// Moves with the same shape differ only in the row they rest on.
+    pub fn shape(&self) -> Vec<(i32, i32)> {
+        let top = self.top();
+        self.block.cells().iter().map(|&(x, y)| (x, y - top)).collect()
+    }
+}
+
+/// The inputs used by `generate`.
+static GENERATE_INPUTS: [Input; 5] = [
+    Input::Left, Input::Right, Input::RotateLeft, Input::RotateRight, Input::SoftDrop
+];
+
+/// Return every resting position `block` can reach on `field`.
+///
+/// The rotation system used is that of `block`. Positions which occupy the
+/// same cells are considered identical, so symmetric pieces (e.g. an `O`
+/// block in any rotation) are only returned once. A position which can be
+/// reached both with and without a spin is returned once for each.
+///
+/// Moves are ordered by the length of their input sequence.
+pub fn generate(field: &Field, block: &Block, wallkick: &Wallkick) -> Vec<Move> {
+    search(field, block, wallkick, &GENERATE_INPUTS)
+}
+
+/// Return every resting position `block` can reach using only the
+/// specified inputs.
+///
+/// See `generate` for details.
+pub fn search(field: &Field, block: &Block, wallkick: &Wallkick, inputs: &[Input]) -> Vec<Move> {
+    let mut moves = Vec::new();
+
+    // Cells of all found resting positions and whether they were spins
+    let mut found = HashSet::new();
+
+    // Each queued state stores the path to reach it and whether the last
+    // input was a rotation.
+    let mut queue = VecDeque::new();
+    let mut seen = HashSet::new();
+
+    if block.collides(field) {
+        return moves;
+    }
+
+    seen.insert((block.x, block.y, block.r as usize, false));
+    queue.push_back((block.clone(), Vec::new(), false));
+
+    while let Some((current, path, rotated)) = queue.pop_front() {
+        let ghost = current.ghost(field);
+        let spin = rotated && ghost.y == current.y;
+
+        if found.insert((ghost.cells(), spin)) {
+            moves.push(Move { block: ghost, inputs: path.clone(), spin });
+        }
+
+        for &input in inputs {
+            let mut next = current.clone();
+
+            if input.apply(&mut next, field, wallkick) &&
+                    seen.insert((next.x, next.y, next.r as usize, input.is_rotation())) {
+                let mut next_path = path.clone();
+                next_path.push(input);
+                queue.push_back((next, next_path, input.is_rotation()));
+            }
+        }
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use import::*;
+    use schema::Schema;
+
+    #[test]
+    fn test_empty_field() {
+        let field = Field::new();
+        let wk = wallkick::new("srs").unwrap();
+
+        let expected = [
+            (block::Id::I, 17), (block::Id::T, 34), (block::Id::L, 34),
+            (block::Id::J, 34), (block::Id::S, 17), (block::Id::Z, 17),
+            (block::Id::O, 9)
+        ];
+
+        for &(id, count) in expected.iter() {
+            let block = Block::new(id, &field);
+            let moves = generate(&field, &block, &*wk);
+
+            let moves = moves.iter().filter(|m| !m.spin).collect::<Vec<_>>();
+            assert_eq!(moves.len(), count, "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn test_tuck() {
+        let (field, block) = Schema::from_string("
+                |          |
+                |   @@     |
+                |  @@      |
+                |          |
+                |          |
+                |######    |
+                |          |
+                |#       ##|
+                ------------
+            ").to_state(rotation_system::new("srs").unwrap());
+
+        let wk = wallkick::new("srs").unwrap();
+        let moves = generate(&field, &block, &*wk);
+
+        // Tucking the block underneath the overhang requires a soft drop
+        let tuck = moves.iter()
+                        .find(|m| m.block.cells() == vec![(5, 7), (6, 6), (6, 7), (7, 6)])
+                        .expect("tuck is reachable");
+
+        assert!(tuck.inputs.contains(&Input::SoftDrop));
+        assert_eq!(tuck.inputs.last(), Some(&Input::Left));
+    }
+
+    #[test]
+    fn test_spin() {
+        let field = Field::new();
+        let block = Block::new(block::Id::T, &field);
+        let wk = wallkick::new("srs").unwrap();
+        let moves = generate(&field, &block, &*wk);
+
+        // Rotating on the floor reaches positions a plain drop also reaches,
+        // and the spin is kept alongside the shorter non-spin path
+        let spins = moves.iter().filter(|m| m.spin).collect::<Vec<_>>();
+        assert!(!spins.is_empty());
+
+        for spin in spins {
+            assert!(moves.iter().any(|m| !m.spin && m.block.cells() == spin.block.cells()));
+        }
+    }
+}
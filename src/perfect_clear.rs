@@ -0,0 +1,524 @@
+//! A perfect clear solver.
+//!
+//! Given a field, the current piece, the hold piece and the upcoming preview
+//! pieces, this searches for a sequence of placements which clears every cell
+//! from the field.
+//!
+//! Placements are found using `movegen`, so only positions reachable with the
+//! specified rotation system and wallkick are considered.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::perfect_clear::Solver;
+//!
+//! let mut field = Field::new();
+//! for x in 0..6 {
+//!     field.set((x, 23), block::Id::I);
+//!     field.set((x, 24), block::Id::I);
+//! }
+//!
+//! let solver = Solver::new(rotation_system::new("srs").unwrap(),
+//!                          wallkick::new("srs").unwrap());
+//!
+//! // Two O blocks fill the remaining 4x2 area
+//! let solution = solver.solve(&field, block::Id::O, None, &[block::Id::O]).unwrap();
+//! assert_eq!(solution.steps.len(), 2);
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use block::{self, Block, Rotation};
+use field::Field;
+use engine::Placement;
+use movegen::{self, Input};
+use rotation_system::RotationSystem;
+use wallkick::Wallkick;
+
+/// A single placement within a `Solution`.
+#[derive(Clone)]
+pub struct Step {
+    /// The placement to perform, compatible with `Engine::place`
+    pub placement: Placement,
+
+    /// The block at its final resting position
+    pub block: Block,
+
+    /// The inputs required to reach the position after any hold
+    pub inputs: Vec<Input>
+}
+
+/// A sequence of placements which results in a perfect clear.
+#[derive(Clone)]
+pub struct Solution {
+    /// The placements in order
+    pub steps: Vec<Step>
+}
+
+impl Solution {
+    /// Return the total number of inputs required by this solution.
+    pub fn inputs(&self) -> usize {
+        self.steps.iter().map(|s| s.inputs.len() + s.placement.hold as usize).sum()
+    }
+}
+
+/// Optional values which can be set when initializing a `Solver`.
+///
+/// The default values are:
+///
+/// ```text
+/// SolverOptions {
+///     max_pieces: 10,
+///     max_height: 4,
+///     hold: true,
+///     limit: 64
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SolverOptions {
+    /// The maximum number of pieces a solution can use
+    pub max_pieces: usize,
+
+    /// The maximum number of rows a solution can fill
+    pub max_height: usize,
+
+    /// Can the hold piece be used?
+    pub hold: bool,
+
+    /// The maximum number of solutions returned by `solve_all`
+    pub limit: usize,
+}
+
+impl Default for SolverOptions {
+    fn default() -> SolverOptions {
+        SolverOptions {
+            max_pieces: 10,
+            max_height: 4,
+            hold: true,
+            limit: 64
+        }
+    }
+}
+
+/// Searches for perfect clears.
+pub struct Solver {
+    /// The rotation system placed blocks use
+    pub rs: Arc<RotationSystem>,
+
+    /// The wallkick used when rotating
+    pub wk: Arc<Wallkick>,
+
+    /// Options used when searching
+    pub op: SolverOptions
+}
+
+/// The current search state.
+struct State<'a> {
+    /// The pieces which can be placed, starting with the current piece
+    queue: &'a [block::Id],
+
+    /// The number of rows which must be filled and cleared
+    height: usize,
+
+    /// The size every empty region must be a multiple of
+    unit: usize,
+
+    /// Placements made so far
+    steps: Vec<Step>,
+
+    /// Positions which have already been searched without finding a solution
+    dead: HashSet<(Vec<u64>, usize, usize, Option<block::Id>)>,
+
+    /// Found solutions
+    solutions: Vec<Solution>,
+
+    /// Maximum number of solutions to find
+    limit: usize
+}
+
+impl Solver {
+    /// Construct a new `Solver` with default options.
+    pub fn new(rs: Arc<RotationSystem>, wk: Arc<Wallkick>) -> Solver {
+        Solver::with_options(rs, wk, SolverOptions { ..Default::default() })
+    }
+
+    /// Construct a new `Solver` with the specified options.
+    pub fn with_options(rs: Arc<RotationSystem>, wk: Arc<Wallkick>,
+                        options: SolverOptions) -> Solver {
+        Solver { rs, wk, op: options }
+    }
+
+    /// Return the best perfect clear found, if any.
+    ///
+    /// The best solution uses the least pieces, and then the least inputs.
+    pub fn solve(&self, field: &Field, current: block::Id, hold: Option<block::Id>,
+                 preview: &[block::Id]) -> Option<Solution> {
+        self.search(field, current, hold, preview, self.op.limit)
+            .into_iter()
+            .min_by_key(|s| (s.steps.len(), s.inputs()))
+    }
+
+    /// Return all perfect clears found, up to the configured limit.
+    ///
+    /// Solutions which place the same pieces in a different order are
+    /// distinct.
+    pub fn solve_all(&self, field: &Field, current: block::Id, hold: Option<block::Id>,
+                     preview: &[block::Id]) -> Vec<Solution> {
+        self.search(field, current, hold, preview, self.op.limit)
+    }
+
+    /// Search all possible clear heights for solutions.
+    fn search(&self, field: &Field, current: block::Id, hold: Option<block::Id>,
+              preview: &[block::Id], limit: usize) -> Vec<Solution> {
+        let mut queue = vec![current];
+        queue.extend(preview);
+
+        let filled = iproduct!(0..field.width, 0..field.height)
+                         .filter(|&(x, y)| field.occupies((x, y)))
+                         .count();
+
+        let stack = (0..field.height).find(|&y| (0..field.width).any(|x| field.occupies((x, y))))
+                                     .map_or(0, |y| field.height - y);
+
+        // Every region must be filled by a combination of the available pieces
+        let sizes = queue.iter().chain(hold.iter())
+                         .map(|&id| self.rs.data(id, Rotation::R0).len())
+                         .collect::<Vec<_>>();
+        let unit = sizes.iter().fold(0, |a, &b| gcd(a, b));
+        let largest = sizes.iter().cloned().max().unwrap_or(0);
+
+        let mut solutions = Vec::new();
+
+        // Try each clear height, fewest rows first
+        for height in stack..self.op.max_height + 1 {
+            let cells = height * field.width;
+            if height == 0 || cells < filled {
+                continue;
+            }
+
+            // The remaining cells must be filled by whole pieces
+            let remaining = cells - filled;
+            if !remaining.is_multiple_of(unit) {
+                continue;
+            }
+
+            let pieces = remaining.div_ceil(largest);
+
+            let available = queue.len() + hold.is_some() as usize;
+            if pieces > self.op.max_pieces || pieces > available {
+                continue;
+            }
+
+            let mut state = State {
+                queue: &queue,
+                height,
+                unit,
+                steps: Vec::new(),
+                dead: HashSet::new(),
+                solutions: Vec::new(),
+                limit: limit - solutions.len()
+            };
+
+            self.dfs(&mut state, field, 0, hold);
+            solutions.extend(state.solutions);
+
+            if solutions.len() >= limit {
+                break;
+            }
+        }
+
+        solutions
+    }
+
+    /// Depth-first search over all placements of the next piece.
+    fn dfs(&self, state: &mut State, field: &Field, next: usize, hold: Option<block::Id>) {
+        if state.solutions.len() >= state.limit {
+            return;
+        }
+
+        if iproduct!(0..field.width, 0..field.height).all(|(x, y)| !field.occupies((x, y))) {
+            state.solutions.push(Solution { steps: state.steps.clone() });
+            return;
+        }
+
+        if next >= state.queue.len() && hold.is_none() {
+            return;
+        }
+
+        // Only failed positions are skipped, since reaching a solved position
+        // by a different path is a different solution.
+        let key = (field.rows().to_vec(), state.height, next, hold);
+        if state.dead.contains(&key) {
+            return;
+        }
+
+        let found = state.solutions.len();
+
+        // Each option is the piece to place, the resulting hold piece, the
+        // index of the next piece and whether a hold was used.
+        let mut options = Vec::new();
+
+        if next < state.queue.len() {
+            options.push((state.queue[next], hold, next + 1, false));
+        }
+
+        if self.op.hold {
+            match hold {
+                Some(id) if next < state.queue.len() => {
+                    options.push((id, Some(state.queue[next]), next + 1, true));
+                },
+                Some(id) => {
+                    // The hold piece is the last piece available
+                    options.push((id, None, next, true));
+                },
+                None if next + 1 < state.queue.len() => {
+                    options.push((state.queue[next + 1], Some(state.queue[next]), next + 2, true));
+                },
+                None => ()
+            }
+        }
+
+        // A hold which swaps identical pieces is the same as no hold
+        options.dedup_by(|a, b| (a.0, a.1, a.2) == (b.0, b.1, b.2));
+
+        for (id, hold, next, held) in options {
+            // Blocks spawn as in the engine, so every solution can be played
+            let block = Block::spawn(id, field, None, self.rs.clone());
+
+            // A position reachable with and without a spin leaves the same
+            // field, so only the shortest path to it is tried
+            let mut tried = HashSet::new();
+
+            for m in movegen::generate(field, &block, &*self.wk) {
+                // The placement must lie within the rows to be cleared
+                let top = field.height - state.height;
+                if m.block.cells().iter().any(|&(_, y)| (y as usize) < top) ||
+                        !tried.insert(m.block.cells()) {
+                    continue;
+                }
+
+                let mut result = field.clone();
+                result.freeze(m.block.clone());
+                let lines = result.clear_lines();
+
+                if !Solver::fillable(&result, state.height - lines, state.unit) {
+                    continue;
+                }
+
+                state.steps.push(Step {
+                    placement: Placement {
+                        x: m.block.x, r: m.block.r, hold: held, spin: m.spin
+                    },
+                    block: m.block.clone(),
+                    inputs: m.inputs.clone()
+                });
+
+                let height = state.height;
+                state.height -= lines;
+                self.dfs(state, &result, next, hold);
+                state.height = height;
+
+                state.steps.pop();
+
+                if state.solutions.len() >= state.limit {
+                    return;
+                }
+            }
+        }
+
+        if state.solutions.len() == found {
+            state.dead.insert(key);
+        }
+    }
+
+    /// Return true if every enclosed empty region in the bottom `height` rows
+    /// is a multiple of `unit` cells in size.
+    fn fillable(field: &Field, height: usize, unit: usize) -> bool {
+        let top = field.height - height;
+        let mut seen = vec![vec![false; field.width]; field.height];
+
+        for (x, y) in iproduct!(0..field.width, top..field.height) {
+            if seen[y][x] || field.occupies((x, y)) {
+                continue;
+            }
+
+            // Flood fill this empty region
+            let mut size = 0;
+            let mut stack = vec![(x, y)];
+            seen[y][x] = true;
+
+            while let Some((a, b)) = stack.pop() {
+                size += 1;
+
+                let mut adjacent = vec![(a + 1, b), (a, b + 1)];
+                if a > 0 {
+                    adjacent.push((a - 1, b));
+                }
+                if b > top {
+                    adjacent.push((a, b - 1));
+                }
+
+                for (c, d) in adjacent {
+                    if c < field.width && d < field.height && !seen[d][c] &&
+                            !field.occupies((c, d)) {
+                        seen[d][c] = true;
+                        stack.push((c, d));
+                    }
+                }
+            }
+
+            if size % unit != 0 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Return the greatest common divisor of `a` and `b`.
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use import::*;
+
+    fn field(rows: &[&str]) -> Field {
+        let mut field = Field::new();
+        let offset = field.height - rows.len();
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    field.set((x, offset + y), block::Id::I);
+                }
+            }
+        }
+
+        field
+    }
+
+    fn solver() -> Solver {
+        Solver::new(rotation_system::new("srs").unwrap(), wallkick::new("srs").unwrap())
+    }
+
+    #[test]
+    fn test_solve() {
+        let field = field(&[
+            "######    ",
+            "######    ",
+        ]);
+
+        let solution = solver().solve(&field, block::Id::I, None, &[block::Id::I]).unwrap();
+        assert_eq!(solution.steps.len(), 2);
+        assert!(solution.steps.iter().all(|s| !s.placement.hold));
+    }
+
+    #[test]
+    fn test_solve_hold() {
+        let field = field(&[
+            "######    ",
+            "######    ",
+        ]);
+
+        let solution = solver().solve(&field, block::Id::T, None,
+                                      &[block::Id::I, block::Id::I]).unwrap();
+        assert_eq!(solution.steps.len(), 2);
+        assert!(solution.steps[0].placement.hold);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let field = field(&[
+            "######    ",
+            "######    ",
+        ]);
+
+        assert!(solver().solve(&field, block::Id::S, None, &[block::Id::Z]).is_none());
+    }
+
+    #[test]
+    fn test_solve_all() {
+        let field = field(&[
+            "######    ",
+            "######    ",
+        ]);
+
+        // Each I piece clears a row, either played directly or after a hold
+        let solutions = solver().solve_all(&field, block::Id::I, None, &[block::Id::I]);
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions.iter().all(|s| s.steps.len() == 2));
+    }
+
+    #[test]
+    fn test_solve_mixed() {
+        let field = field(&[
+            "#######   ",
+            "#######   ",
+        ]);
+
+        let solver = Solver::new(rotation_system::new("generic").unwrap(),
+                                 wallkick::new("srs").unwrap());
+
+        // The 3x2 area is filled by a pentomino and a monomino
+        let p = block::Id::from_name("P").unwrap();
+        let i1 = block::Id::from_name("I1").unwrap();
+        let solution = solver.solve(&field, p, None, &[i1]).unwrap();
+        assert_eq!(solution.steps.len(), 2);
+
+        let l3 = block::Id::from_name("L3").unwrap();
+        assert!(solver.solve(&field, p, None, &[l3]).is_none());
+    }
+
+    #[test]
+    fn test_playable() {
+        let mut engine = Engine::new(EngineOptions {
+            rotation_system_name: "ars".to_string(),
+            wallkick_name: "srs".to_string(),
+            randomizer_sequence: "JO".to_string(),
+            ..Default::default()
+        });
+        engine.fd = field(&[
+            "#####     ",
+            "#######   ",
+        ]);
+
+        // Solutions start from where the engine spawns each piece
+        let solver = Solver::new(engine.rs.clone(), engine.wk.clone());
+        let solution = solver.solve(&engine.fd, block::Id::J, None, &[block::Id::O]).unwrap();
+
+        for step in &solution.steps {
+            engine.advance().unwrap();
+
+            let mut block = engine.bk.clone();
+            for input in &step.inputs {
+                input.apply(&mut block, &engine.fd, &*engine.wk);
+            }
+            assert_eq!(block.ghost(&engine.fd).cells(), step.block.cells());
+
+            engine.place(step.placement).unwrap();
+        }
+        assert!(engine.fd.rows().iter().all(|&row| row == 0));
+    }
+
+    #[test]
+    fn test_solve_all_orders() {
+        let field = field(&[
+            "##        ",
+            "##        ",
+        ]);
+
+        let options = SolverOptions { hold: false, limit: 1000, ..Default::default() };
+        let solver = Solver::with_options(rotation_system::new("srs").unwrap(),
+                                          wallkick::new("srs").unwrap(), options);
+
+        // The four O blocks can be placed in any order
+        let solutions = solver.solve_all(&field, block::Id::O, None, &[block::Id::O; 3]);
+        assert_eq!(solutions.len(), 24);
+    }
+}
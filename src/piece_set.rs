@@ -0,0 +1,270 @@
+//! Piece sets.
+//!
+//! Every `block::Id` refers to an entry in a global piece table. The table
+//! contains the seven tetrominoes, the monomino through triominoes and the 18
+//! one-sided pentominoes. Further pieces can be added at runtime with
+//! `register`.
+//!
+//! A `PieceSet` is a selection of pieces which a game is played with. This is
+//! used to construct randomizers.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//!
+//! let set = piece_set::new("pentomino").unwrap();
+//! assert_eq!(set.pieces.len(), 18);
+//!
+//! let id = block::Id::from_name("X").unwrap();
+//! assert!(set.pieces.contains(&id));
+//! assert_eq!(id.shape().len(), 5);
+//! ```
+
+use std::sync::RwLock;
+
+use block::Id;
+
+/// A piece in the global piece table.
+///
+/// The shape is the offsets of the piece in its spawn orientation. Rotation
+/// systems which do not define their own data rotate this shape within its
+/// bounding square.
+///
+/// Pieces are never removed from the table, so they are only ever borrowed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Piece {
+    /// The unique name of this piece
+    pub name: &'static str,
+
+    /// The offsets of this piece in its spawn orientation
+    pub shape: &'static [(usize, usize)],
+
+    /// The default `(r, g, b)` colour of this piece
+    pub colour: (u8, u8, u8)
+}
+
+/// The number of built-in pieces.
+const BUILTIN_COUNT: usize = 29;
+
+/// Construct a built-in piece.
+macro_rules! piece {
+    ($name:expr, $shape:expr, $colour:expr) => {
+        Piece { name: $name, shape: $shape, colour: $colour }
+    }
+}
+
+/// The built-in pieces. The index of each is its `Id`.
+static BUILTIN: [Piece; BUILTIN_COUNT] = [
+    // Tetrominoes
+    piece!("I", &[(0, 1), (1, 1), (2, 1), (3, 1)], (0, 255, 255)),
+    piece!("T", &[(0, 1), (1, 0), (1, 1), (2, 1)], (128, 0, 128)),
+    piece!("L", &[(0, 1), (1, 1), (2, 0), (2, 1)], (255, 165, 0)),
+    piece!("J", &[(0, 0), (0, 1), (1, 1), (2, 1)], (0, 0, 255)),
+    piece!("S", &[(0, 1), (1, 0), (1, 1), (2, 0)], (128, 255, 0)),
+    piece!("Z", &[(0, 0), (1, 0), (1, 1), (2, 1)], (255, 0, 0)),
+    piece!("O", &[(0, 0), (0, 1), (1, 0), (1, 1)], (255, 255, 0)),
+
+    // Monomino, domino and triominoes
+    piece!("I1", &[(0, 0)], (200, 200, 200)),
+    piece!("I2", &[(0, 0), (1, 0)], (160, 160, 255)),
+    piece!("I3", &[(0, 1), (1, 1), (2, 1)], (0, 200, 200)),
+    piece!("L3", &[(0, 0), (0, 1), (1, 1)], (255, 200, 100)),
+
+    // One-sided pentominoes, with mirrored pieces marked by a `'`
+    piece!("F", &[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)], (200, 80, 80)),
+    piece!("F'", &[(0, 0), (1, 0), (1, 1), (2, 1), (1, 2)], (80, 200, 80)),
+    piece!("I5", &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2)], (0, 160, 255)),
+    piece!("L5", &[(3, 0), (0, 1), (1, 1), (2, 1), (3, 1)], (255, 120, 0)),
+    piece!("L5'", &[(0, 0), (0, 1), (1, 1), (2, 1), (3, 1)], (0, 80, 200)),
+    piece!("N", &[(0, 0), (1, 0), (1, 1), (2, 1), (3, 1)], (200, 0, 120)),
+    piece!("N'", &[(2, 0), (3, 0), (0, 1), (1, 1), (2, 1)], (120, 0, 200)),
+    piece!("P", &[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1)], (255, 100, 180)),
+    piece!("P'", &[(0, 0), (1, 0), (2, 0), (1, 1), (2, 1)], (180, 100, 255)),
+    piece!("T5", &[(0, 0), (1, 0), (2, 0), (1, 1), (1, 2)], (160, 0, 160)),
+    piece!("U", &[(0, 0), (2, 0), (0, 1), (1, 1), (2, 1)], (220, 220, 0)),
+    piece!("V", &[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)], (0, 200, 120)),
+    piece!("W", &[(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)], (120, 200, 0)),
+    piece!("X", &[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)], (255, 60, 60)),
+    piece!("Y", &[(2, 0), (0, 1), (1, 1), (2, 1), (3, 1)], (255, 200, 0)),
+    piece!("Y'", &[(1, 0), (0, 1), (1, 1), (2, 1), (3, 1)], (200, 255, 0)),
+    piece!("Z5", &[(0, 0), (1, 0), (1, 1), (1, 2), (2, 2)], (255, 40, 120)),
+    piece!("Z5'", &[(1, 0), (2, 0), (1, 1), (0, 2), (1, 2)], (40, 255, 120)),
+];
+
+/// Pieces added with `register`. The `Id` of each follows the built-ins.
+///
+/// The name and shape of a registered piece are leaked so it can be borrowed
+/// for the rest of the program, like a built-in.
+static REGISTERED: RwLock<Vec<&'static Piece>> = RwLock::new(Vec::new());
+
+/// Return the piece with the specified `Id`, if it exists.
+pub fn get(id: Id) -> Option<&'static Piece> {
+    let index = id.index();
+
+    if index < BUILTIN_COUNT {
+        Some(&BUILTIN[index])
+    }
+    else {
+        REGISTERED.read().unwrap().get(index - BUILTIN_COUNT).cloned()
+    }
+}
+
+/// Return the number of pieces in the piece table.
+pub fn count() -> usize {
+    BUILTIN_COUNT + REGISTERED.read().unwrap().len()
+}
+
+/// Return the `Id` of the piece with the specified name.
+pub fn find(name: &str) -> Option<Id> {
+    if let Some(index) = BUILTIN.iter().position(|p| p.name == name) {
+        return Some(Id::from(index));
+    }
+
+    REGISTERED.read().unwrap().iter()
+              .position(|p| p.name == name)
+              .map(|index| Id::from(BUILTIN_COUNT + index))
+}
+
+/// Add a new piece to the piece table and return its `Id`.
+///
+/// If a piece with the same name already exists, its `Id` is returned if the
+/// shape matches, otherwise an error is returned.
+///
+/// ## Examples
+///
+/// ```
+/// use tetrs::import::*;
+///
+/// let plus = piece_set::register("plus", &[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
+///                                (255, 255, 255)).unwrap();
+///
+/// assert_eq!(block::Id::from_name("plus"), Some(plus));
+/// assert!(piece_set::register("T", &[(0, 0)], (0, 0, 0)).is_err());
+/// ```
+pub fn register(name: &str, shape: &[(usize, usize)], colour: (u8, u8, u8))
+        -> Result<Id, String> {
+    if name.is_empty() || shape.is_empty() {
+        return Err("a piece requires a name and a shape".to_string());
+    }
+
+    // Hold the lock so a piece cannot be registered twice concurrently
+    let mut registered = REGISTERED.write().unwrap();
+
+    let existing = BUILTIN.iter()
+                       .position(|p| p.name == name)
+                       .map(|index| (index, BUILTIN[index].shape))
+                       .or_else(|| {
+                           registered.iter().position(|p| p.name == name)
+                                     .map(|index| (BUILTIN_COUNT + index,
+                                                   registered[index].shape))
+                       });
+
+    match existing {
+        Some((index, existing)) if existing == shape => Ok(Id::from(index)),
+        Some(_) => Err(format!("piece already exists with a different shape: {}", name)),
+        None => {
+            let piece = Piece {
+                name: Box::leak(name.to_string().into_boxed_str()),
+                shape: Box::leak(shape.to_vec().into_boxed_slice()),
+                colour
+            };
+
+            registered.push(Box::leak(Box::new(piece)));
+            Ok(Id::from(BUILTIN_COUNT + registered.len() - 1))
+        }
+    }
+}
+
+/// A selection of pieces to play with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceSet {
+    /// The pieces in this set
+    pub pieces: Vec<Id>
+}
+
+impl PieceSet {
+    /// Construct a piece set from piece names.
+    pub fn from_names(names: &[&str]) -> Result<PieceSet, String> {
+        let mut pieces = Vec::new();
+        for name in names {
+            match Id::from_name(name) {
+                Some(id) => pieces.push(id),
+                None => return Err(format!("unknown piece: {}", name))
+            }
+        }
+
+        if pieces.is_empty() {
+            Err("a piece set requires at least one piece".to_string())
+        }
+        else {
+            Ok(PieceSet { pieces })
+        }
+    }
+
+    /// Return the size of the smallest and largest pieces in this set.
+    pub fn size_range(&self) -> (usize, usize) {
+        let sizes = self.pieces.iter().map(|id| id.shape().len());
+        (sizes.clone().min().unwrap_or(0), sizes.max().unwrap_or(0))
+    }
+}
+
+/// Factory function for constructing a piece set from name.
+///
+/// # Names
+///  - `tetromino`
+///  - `pentomino`
+///  - `triomino`
+///  - `domino`
+///  - `monomino`
+///  - `mixed` (every built-in piece)
+///
+/// Any other name is treated as a comma separated list of piece names (e.g.
+/// `"I,T,X"`).
+pub fn new(name: &str) -> Result<PieceSet, String> {
+    let range = match name {
+        "tetromino" => 0..7,
+        "monomino" => 7..8,
+        "domino" => 8..9,
+        "triomino" => 9..11,
+        "pentomino" => 11..BUILTIN_COUNT,
+        "mixed" => 0..BUILTIN_COUNT,
+        _ => return PieceSet::from_names(&name.split(',').map(|s| s.trim()).collect::<Vec<_>>())
+    };
+
+    Ok(PieceSet { pieces: range.map(Id::from).collect() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        // Every piece name is unique and every shape is unique
+        let pieces = new("mixed").unwrap().pieces;
+        for (i, a) in pieces.iter().enumerate() {
+            for b in &pieces[i + 1..] {
+                assert!(a.name() != b.name());
+                assert!(a.shape() != b.shape());
+            }
+        }
+
+        assert_eq!(new("tetromino").unwrap().pieces, Id::variants());
+        assert_eq!(new("triomino").unwrap().size_range(), (3, 3));
+        assert_eq!(new("I, O, X").unwrap().size_range(), (4, 5));
+        assert!(new("I,Q").is_err());
+    }
+
+    #[test]
+    fn test_register() {
+        let id = register("test-domino", &[(0, 0), (0, 1)], (1, 2, 3)).unwrap();
+        assert_eq!(register("test-domino", &[(0, 0), (0, 1)], (0, 0, 0)), Ok(id));
+
+        // Registered pieces are borrowed from the table like built-ins
+        assert!(::std::ptr::eq(id.shape(), get(id).unwrap().shape));
+        assert_eq!(id.name(), "test-domino");
+        assert_eq!(format!("{:?}", id), "test-domino");
+        assert_eq!(id.colour(), (1, 2, 3));
+    }
+}
@@ -0,0 +1,287 @@
+//! Statistical analysis of randomizers.
+//!
+//! A randomizer is run for a number of independent games from a fixed seed
+//! and the generated pieces are summarised in a `Report`. Since every game is
+//! seeded, a report is reproducible and can be compared against a previous
+//! report to catch regressions.
+//!
+//! ## Examples
+//!
+//! ```
+//! use tetrs::import::*;
+//! use tetrs::randomizer::analysis::{self, AnalysisOptions};
+//!
+//! let options = AnalysisOptions { games: 10, length: 70, ..Default::default() };
+//! let report = analysis::analyse("bag", block::Id::variants(), &options).unwrap();
+//!
+//! assert_eq!(report.pieces, 700);
+//! assert!(report.stats.iter().all(|s| s.count == 100));
+//! assert!(report.stats.iter().all(|s| s.longest_drought <= 12));
+//! ```
+
+use std::collections::BTreeMap;
+use serde_json;
+
+use block::Id;
+use randomizer;
+
+/// Options for a randomizer analysis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnalysisOptions {
+    /// The seed of the first game. Each following game uses the next seed.
+    pub seed: u64,
+
+    /// How many independent games are generated
+    pub games: usize,
+
+    /// How many pieces are generated per game
+    pub length: usize
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions { seed: 0, games: 1000, length: 1000 }
+    }
+}
+
+/// Statistics for a single piece.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PieceStats {
+    /// The piece these statistics are for
+    pub id: Id,
+
+    /// How many times this piece was generated
+    pub count: u64,
+
+    /// The proportion of all pieces which were this piece
+    pub frequency: f64,
+
+    /// How many games started with this piece
+    pub first: u64,
+
+    /// `droughts[n]` is the number of times exactly `n` other pieces were
+    /// generated between two of this piece
+    pub droughts: Vec<u64>,
+
+    /// The most pieces generated between two of this piece
+    pub longest_drought: usize,
+
+    /// The mean number of pieces generated between two of this piece
+    pub mean_drought: f64
+}
+
+impl PieceStats {
+    fn new(id: Id) -> PieceStats {
+        PieceStats {
+            id, count: 0, frequency: 0.0, first: 0, droughts: Vec::new(),
+            longest_drought: 0, mean_drought: 0.0
+        }
+    }
+}
+
+/// The results of a randomizer analysis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Report {
+    /// The name of the analysed randomizer
+    pub randomizer: String,
+
+    /// The options the analysis was run with
+    pub options: AnalysisOptions,
+
+    /// The total number of pieces generated
+    pub pieces: u64,
+
+    /// The probability that a piece is the same as the previous piece
+    pub repeat: f64,
+
+    /// `sz_floods[n]` is the number of times exactly `n` S or Z pieces were
+    /// generated in a row
+    pub sz_floods: Vec<u64>,
+
+    /// Statistics for each piece, ordered by `Id`
+    pub stats: Vec<PieceStats>
+}
+
+/// Increment the entry at `index` of a histogram, growing it if required.
+fn record(histogram: &mut Vec<u64>, index: usize) {
+    if histogram.len() <= index {
+        histogram.resize(index + 1, 0);
+    }
+    histogram[index] += 1;
+}
+
+/// Analyse the named randomizer using the specified pieces.
+///
+/// See `randomizer::new` for the available names. Any piece generated which
+/// is not in `pieces` is included in the report.
+pub fn analyse(name: &str, pieces: &[Id], options: &AnalysisOptions) -> Result<Report, String> {
+    let mut stats = pieces.iter().map(|&id| (id, PieceStats::new(id))).collect::<BTreeMap<_, _>>();
+    let mut sz_floods = Vec::new();
+    let mut repeats = 0;
+
+    for game in 0..options.games {
+        let seed = options.seed.wrapping_add(game as u64);
+        let mut randomizer = randomizer::with_seed(name, 0, pieces, seed)?;
+
+        let mut last_seen = BTreeMap::new();
+        let mut prev = Id::None;
+        let mut flood = 0;
+
+        for i in 0..options.length {
+            let id = randomizer.next();
+            let piece = stats.entry(id).or_insert_with(|| PieceStats::new(id));
+
+            piece.count += 1;
+            if i == 0 {
+                piece.first += 1;
+            }
+
+            if let Some(last) = last_seen.insert(id, i) {
+                record(&mut piece.droughts, i - last - 1);
+            }
+
+            if id == prev {
+                repeats += 1;
+            }
+            prev = id;
+
+            if id == Id::S || id == Id::Z {
+                flood += 1;
+            }
+            else if flood != 0 {
+                record(&mut sz_floods, flood);
+                flood = 0;
+            }
+        }
+
+        if flood != 0 {
+            record(&mut sz_floods, flood);
+        }
+    }
+
+    let total = (options.games * options.length) as u64;
+    let pairs = (options.games * options.length.saturating_sub(1)) as u64;
+
+    let stats = stats.into_values().map(|mut piece| {
+        let droughts = piece.droughts.iter().sum::<u64>();
+        let length = piece.droughts.iter().enumerate()
+                          .map(|(n, &count)| n as u64 * count)
+                          .sum::<u64>();
+
+        piece.frequency = ratio(piece.count, total);
+        piece.longest_drought = piece.droughts.len().saturating_sub(1);
+        piece.mean_drought = ratio(length, droughts);
+        piece
+    });
+
+    Ok(Report {
+        randomizer: name.to_string(),
+        options: options.clone(),
+        pieces: total,
+        repeat: ratio(repeats, pairs),
+        sz_floods,
+        stats: stats.collect()
+    })
+}
+
+/// Return `a / b`, or 0 if `b` is 0.
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 { 0.0 } else { a as f64 / b as f64 }
+}
+
+impl Report {
+    /// Return this report as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Return a CSV table with a summary row per piece.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "piece,count,frequency,first,longest_drought,mean_drought\n".to_string();
+
+        for piece in &self.stats {
+            csv += &format!("{},{},{},{},{},{}\n", piece.id.name(), piece.count,
+                            piece.frequency, piece.first, piece.longest_drought,
+                            piece.mean_drought);
+        }
+
+        csv
+    }
+
+    /// Return a CSV table of the drought distribution, with a row per
+    /// drought length and a column per piece.
+    pub fn drought_csv(&self) -> String {
+        let names = self.stats.iter().map(|piece| piece.id.name()).collect::<Vec<_>>();
+        let mut csv = format!("length,{}\n", names.join(","));
+
+        let longest = self.stats.iter().map(|piece| piece.droughts.len()).max().unwrap_or(0);
+        for n in 0..longest {
+            let counts = self.stats.iter()
+                             .map(|piece| piece.droughts.get(n).cloned().unwrap_or(0).to_string())
+                             .collect::<Vec<_>>();
+            csv += &format!("{},{}\n", n, counts.join(","));
+        }
+
+        csv
+    }
+
+    /// Return the statistics of the specified piece.
+    pub fn piece(&self, id: Id) -> Option<&PieceStats> {
+        self.stats.iter().find(|piece| piece.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(name: &str) -> Report {
+        let options = AnalysisOptions { seed: 1, games: 100, length: 1000 };
+        analyse(name, Id::variants(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_reproducible() {
+        assert_eq!(run("tgm1"), run("tgm1"));
+
+        let options = AnalysisOptions { seed: 2, games: 100, length: 1000 };
+        assert!(analyse("tgm1", Id::variants(), &options).unwrap() != run("tgm1"));
+
+        let report = run("bag");
+        assert_eq!(report.to_csv().lines().count(), 8);
+        assert_eq!(report.drought_csv().lines().count(), 14);
+        assert_eq!(::serde_json::from_str::<Report>(&report.to_json()).unwrap(), report);
+    }
+
+    #[test]
+    fn test_characteristics() {
+        let memoryless = run("memoryless");
+        let gameboy = run("gameboy");
+        let tgm1 = run("tgm1");
+        let tgm2 = run("tgm2");
+
+        // A memoryless randomizer repeats 1 in 7 pieces. The Gameboy steps
+        // through the pieces by a roll of 0..39 divided by 5, which lands on
+        // the previous piece for 5 of the 39 rolls.
+        assert!((memoryless.repeat - 1.0 / 7.0).abs() < 0.005, "{}", memoryless.repeat);
+        assert!((gameboy.repeat - 5.0 / 39.0).abs() < 0.005, "{}", gameboy.repeat);
+
+        // More rolls make repeats and S/Z floods less likely
+        assert!(tgm2.repeat < tgm1.repeat, "{} {}", tgm2.repeat, tgm1.repeat);
+        assert!(tgm1.repeat < memoryless.repeat / 4.0, "{}", tgm1.repeat);
+        assert!(tgm1.sz_floods.len() < memoryless.sz_floods.len());
+        assert!(tgm2.sz_floods.len() <= tgm1.sz_floods.len());
+
+        // The first piece is rarely an S, Z or O
+        let first = [Id::S, Id::Z, Id::O].iter()
+                                         .map(|&id| tgm1.piece(id).unwrap().first)
+                                         .sum::<u64>();
+        assert!(first < 10, "{}", first);
+
+        // Every piece is generated equally often
+        for report in &[memoryless, gameboy, tgm1, tgm2] {
+            assert!(report.stats.iter().all(|s| (s.frequency - 1.0 / 7.0).abs() < 0.01));
+        }
+    }
+}
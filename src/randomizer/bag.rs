@@ -1,16 +1,17 @@
 //! Implements a 7-element bag randomizer.
 
-use std::collections::VecDeque;
-use rand::{self, Rng};
+use rand::Rng;
+use rand::prng::XorShiftRng;
 use block::Id;
-use randomizer::Randomizer;
+use randomizer::{self, Randomizer, Lookahead};
 
 gen_rand!(BagRandomizer);
 
 /// A generic bag randomizer.
 ///
-/// This randomizer generates sequences of all 7-blocks and shuffles them,
-/// allowing a maximum distance between block sightings of 13.
+/// This randomizer generates sequences of every piece and shuffles them. With
+/// the 7 tetrominoes this allows a maximum distance between block sightings of
+/// 13.
 ///
 /// ```
 /// use tetrs::import::*;
@@ -25,37 +26,71 @@ gen_rand!(BagRandomizer);
 /// let previews = bag.preview(4); // Get upcoming 4 pieces
 /// let piece2 = bag.next();
 /// ```
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BagRandomizer {
-    /// The lookahead buffer.
-    lookahead: VecDeque<Id>,
+    /// The previewed pieces
+    lookahead: Lookahead,
 
     /// The rng used to generate random values
-    rng: rand::ThreadRng,
+    rng: XorShiftRng,
 
     /// The current index of the bag
     head: usize,
 
     /// The pieces in the bag
-    data: [Id; 7],
+    data: Vec<Id>,
 }
 
 impl BagRandomizer {
     /// Generate a new `BagRandomizer` instance.
     pub fn new(lookahead: usize) -> Self {
+        BagRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Generate a new `BagRandomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> Self {
+        BagRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Generate a new `BagRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> Self {
+        assert!(!pieces.is_empty());
+
         let mut bag = BagRandomizer {
-            lookahead: VecDeque::with_capacity(lookahead),
-            rng: rand::thread_rng(),
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
             head: 0,
-            data: [Id::None; 7],
+            data: pieces.to_vec(),
         };
 
-        // Pre-fill bag with all blocks and shuffle
-        bag.data.clone_from_slice(Id::variants());
         bag.rng.shuffle(&mut bag.data[..]);
         bag
     }
 
+    /// Generate a new `BagRandomizer` instance whose first piece is not one
+    /// of `avoid`.
+    ///
+    /// The first bag is reshuffled until it starts with another piece. If
+    /// every piece is in `avoid` then the first bag is left as is.
+    pub fn with_first_avoiding(lookahead: usize, pieces: &[Id], seed: u64, avoid: &[Id]) -> Self {
+        let mut bag = BagRandomizer::with_seed(lookahead, pieces, seed);
+
+        if pieces.iter().any(|id| !avoid.contains(id)) {
+            while avoid.contains(&bag.data[0]) {
+                bag.rng.shuffle(&mut bag.data[..]);
+            }
+        }
+
+        bag
+    }
+
+    /// Return the pieces remaining in the current bag.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.data[self.head..])
+    }
+
     /// Generate the next block in the sequence
     fn next_block(&mut self) -> Id {
         let id = self.data[self.head];
@@ -96,4 +131,37 @@ mod tests {
         seq_test!(randomizer);
         seq_test!(randomizer);
     }
+
+    #[test]
+    fn test_state() {
+        let mut randomizer = BagRandomizer::new(7);
+        assert_eq!(randomizer.possible().len(), 7);
+
+        // The previewed pieces are no longer possible in this bag
+        let preview = randomizer.preview(1);
+        let possible = randomizer.possible();
+        assert_eq!(possible.len(), 6);
+        assert!(!possible.contains(&preview[0]));
+
+        // A clone generates the same sequence
+        let mut clone = randomizer.clone_box();
+        for _ in 0..14 {
+            assert_eq!(randomizer.next(), clone.next());
+        }
+
+        assert!(randomizer.restore("{}").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        // A fresh randomizer fills the whole preview, across bags
+        let mut randomizer = BagRandomizer::new(10);
+        let preview = randomizer.preview(10);
+        assert_eq!(preview.len(), 10);
+        assert_eq!(randomizer.preview(5), &preview[..5]);
+
+        for &id in &preview {
+            assert_eq!(randomizer.next(), id);
+        }
+    }
 }
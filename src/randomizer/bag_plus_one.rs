@@ -0,0 +1,105 @@
+//! Implements a bag randomizer with an extra random piece.
+
+use rand::Rng;
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead};
+
+gen_rand!(BagPlusOneRandomizer);
+
+/// A "7+1" bag randomizer.
+///
+/// Each bag contains every piece once, plus one extra piece chosen at random,
+/// and is then shuffled.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BagPlusOneRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// The current index of the bag
+    head: usize,
+
+    /// The shuffled pieces in the bag, including the extra piece
+    data: Vec<Id>,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
+}
+
+impl BagPlusOneRandomizer {
+    /// Generate a new `BagPlusOneRandomizer` instance.
+    pub fn new(lookahead: usize) -> Self {
+        BagPlusOneRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Generate a new `BagPlusOneRandomizer` instance using the specified
+    /// pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> Self {
+        BagPlusOneRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Generate a new `BagPlusOneRandomizer` instance using the specified
+    /// pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> Self {
+        assert!(!pieces.is_empty());
+
+        let mut bag = BagPlusOneRandomizer {
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            head: 0,
+            data: Vec::new(),
+            pieces: pieces.to_vec()
+        };
+
+        bag.fill();
+        bag
+    }
+
+    /// Refill and shuffle the bag.
+    fn fill(&mut self) {
+        let extra = *self.rng.choose(&self.pieces).unwrap();
+
+        self.data = self.pieces.clone();
+        self.data.push(extra);
+        self.rng.shuffle(&mut self.data[..]);
+        self.head = 0;
+    }
+
+    /// Return the pieces remaining in the current bag.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.data[self.head..])
+    }
+
+    /// Generate the next block in the sequence
+    fn next_block(&mut self) -> Id {
+        let id = self.data[self.head];
+
+        self.head += 1;
+        if self.head == self.data.len() {
+            self.fill();
+        }
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomizer::Randomizer;
+
+    #[test]
+    fn test_sequence() {
+        let mut randomizer = BagPlusOneRandomizer::with_seed(1, Id::variants(), 0);
+
+        for _ in 0..10 {
+            let bag = (0..8).map(|_| randomizer.next()).collect::<Vec<_>>();
+            assert!(Id::variants().iter().all(|id| bag.contains(id)));
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Implements a Tetris DS style randomizer.
+
+use block::Id;
+use randomizer::{self, BagRandomizer, Randomizer, Lookahead};
+
+gen_rand!(DSRandomizer);
+
+/// A bag randomizer in the style of Tetris DS and Tetris Online Poland.
+///
+/// This behaves like `BagRandomizer`, except the first bag is reshuffled
+/// until it does not start with an S or Z, so the game never opens with an
+/// overhang.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DSRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The underlying bag, which keeps no preview of its own
+    bag: BagRandomizer
+}
+
+impl DSRandomizer {
+    /// Generate a new `DSRandomizer` instance.
+    pub fn new(lookahead: usize) -> Self {
+        DSRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Generate a new `DSRandomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> Self {
+        DSRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Generate a new `DSRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> Self {
+        DSRandomizer {
+            lookahead: Lookahead::new(lookahead),
+            bag: BagRandomizer::with_first_avoiding(0, pieces, seed, &[Id::S, Id::Z])
+        }
+    }
+
+    /// Return the pieces remaining in the current bag.
+    fn possible_blocks(&self) -> Vec<Id> {
+        self.bag.possible()
+    }
+
+    /// Generate the next block in the sequence
+    fn next_block(&mut self) -> Id {
+        self.bag.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomizer::Randomizer;
+
+    #[test]
+    fn test_first() {
+        for seed in 0..100 {
+            let mut randomizer = DSRandomizer::with_seed(1, Id::variants(), seed);
+            assert!(![Id::S, Id::Z].contains(&randomizer.next()));
+        }
+    }
+}
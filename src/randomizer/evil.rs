@@ -0,0 +1,108 @@
+//! Implements an evil randomizer.
+
+use rand::Rng;
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead, Context};
+
+gen_rand!(EvilRandomizer, adaptive);
+
+/// A randomizer which chooses the worst piece for the field, in the style of
+/// bastet.
+///
+/// Every piece is scored by its best placement on the field, and the piece
+/// with the lowest score is chosen. If multiple pieces are equally bad, one is
+/// chosen at random. Without a context, a piece is chosen at random.
+///
+/// A piece is not chosen until the field it is placed on is known, so this
+/// randomizer has no preview.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EvilRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
+}
+
+impl Default for EvilRandomizer {
+    fn default() -> EvilRandomizer {
+        EvilRandomizer::new()
+    }
+}
+
+impl EvilRandomizer {
+    /// Return a new `EvilRandomizer` instance.
+    pub fn new() -> EvilRandomizer {
+        EvilRandomizer::with_pieces(Id::variants())
+    }
+
+    /// Return a new `EvilRandomizer` instance using the specified pieces.
+    pub fn with_pieces(pieces: &[Id]) -> EvilRandomizer {
+        EvilRandomizer::with_seed(pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `EvilRandomizer` instance using the specified pieces.
+    ///
+    /// The pieces chosen between equally bad pieces are determined by `seed`.
+    pub fn with_seed(pieces: &[Id], seed: u64) -> EvilRandomizer {
+        assert!(!pieces.is_empty());
+
+        EvilRandomizer {
+            lookahead: Lookahead::new(0),
+            rng: randomizer::new_rng(seed),
+            pieces: randomizer::unique(pieces)
+        }
+    }
+
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        self.pieces.clone()
+    }
+
+    fn next_block(&mut self) -> Id {
+        *self.rng.choose(&self.pieces).unwrap()
+    }
+
+    fn next_block_with(&mut self, context: &Context) -> Id {
+        let scores = self.pieces.iter()
+                         .map(|&id| (id, (context.score)(id)))
+                         .collect::<Vec<_>>();
+
+        let worst = scores.iter().map(|&(_, score)| score).fold(f64::INFINITY, f64::min);
+        let candidates = scores.into_iter()
+                               .filter(|&(_, score)| score == worst)
+                               .map(|(id, _)| id)
+                               .collect::<Vec<_>>();
+
+        *self.rng.choose(&candidates).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evil() {
+        // Only an I fits the field
+        let score = |id| if id == Id::I { 1.0 } else { 0.0 };
+        let context = Context { score: &score };
+
+        let mut randomizer = EvilRandomizer::with_seed(Id::variants(), 0);
+        assert_eq!(randomizer.max_preview(), 0);
+        assert!((0..100).all(|_| randomizer.next_with(&context) != Id::I));
+
+        // Equally bad pieces are all chosen
+        let score = |id| if id == Id::I || id == Id::O { 0.0 } else { 1.0 };
+        let context = Context { score: &score };
+
+        let mut randomizer = EvilRandomizer::with_seed(Id::variants(), 0);
+        let ids = (0..100).map(|_| randomizer.next_with(&context)).collect::<Vec<_>>();
+        assert!(ids.iter().all(|&id| id == Id::I || id == Id::O));
+        assert!(ids.contains(&Id::I) && ids.contains(&Id::O));
+    }
+}
@@ -0,0 +1,129 @@
+//! Implements a friendly randomizer.
+
+use rand::Rng;
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead, Context};
+
+gen_rand!(FriendlyRandomizer, adaptive);
+
+/// A randomizer which favours pieces that fit the field, for training.
+///
+/// Every piece is scored by its best placement on the field and ranked. The
+/// best fitting piece is the most likely to be chosen and the worst fitting is
+/// the least likely, but every piece can still be generated. Without a
+/// context, a piece is chosen at random.
+///
+/// The ranking depends on the current field, so there is no preview.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FriendlyRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
+}
+
+impl Default for FriendlyRandomizer {
+    fn default() -> FriendlyRandomizer {
+        FriendlyRandomizer::new()
+    }
+}
+
+impl FriendlyRandomizer {
+    /// Return a new `FriendlyRandomizer` instance.
+    pub fn new() -> FriendlyRandomizer {
+        FriendlyRandomizer::with_pieces(Id::variants())
+    }
+
+    /// Return a new `FriendlyRandomizer` instance using the specified pieces.
+    pub fn with_pieces(pieces: &[Id]) -> FriendlyRandomizer {
+        FriendlyRandomizer::with_seed(pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `FriendlyRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence for a given sequence of fields is determined by
+    /// `seed`.
+    pub fn with_seed(pieces: &[Id], seed: u64) -> FriendlyRandomizer {
+        assert!(!pieces.is_empty());
+
+        FriendlyRandomizer {
+            lookahead: Lookahead::new(0),
+            rng: randomizer::new_rng(seed),
+            pieces: randomizer::unique(pieces)
+        }
+    }
+
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        self.pieces.clone()
+    }
+
+    fn next_block(&mut self) -> Id {
+        *self.rng.choose(&self.pieces).unwrap()
+    }
+
+    fn next_block_with(&mut self, context: &Context) -> Id {
+        // A score which is not a number is treated as the worst possible
+        let scores = self.pieces.iter()
+                         .map(|&id| (id, (context.score)(id)))
+                         .map(|(id, score)| (id, if score.is_nan() { f64::NEG_INFINITY } else { score }))
+                         .collect::<Vec<_>>();
+
+        // Weight each piece by its rank, from 1 for the worst piece up to the
+        // number of pieces for the best. Pieces with equal scores share the
+        // lowest of their ranks.
+        let weights = scores.iter()
+                            .map(|&(id, score)| {
+                                let below = scores.iter().filter(|&&(_, s)| s < score).count();
+                                (id, (below + 1) as f64)
+                            })
+                            .collect::<Vec<_>>();
+
+        randomizer::choose_weighted(&mut self.rng, &weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_friendly() {
+        // Only an I fits the field
+        let score = |id| if id == Id::I { 1.0 } else { 0.0 };
+        let context = Context { score: &score };
+
+        // The I is generated far more often than with a uniform choice
+        let mut randomizer = FriendlyRandomizer::with_seed(Id::variants(), 0);
+        assert_eq!(randomizer.max_preview(), 0);
+        let count = (0..700).filter(|_| randomizer.next_with(&context) == Id::I).count();
+        assert!(count > 150, "{}", count);
+
+        let mut randomizer = FriendlyRandomizer::with_seed(Id::variants(), 0);
+        let count = (0..700).filter(|_| randomizer.next() == Id::I).count();
+        assert!(count < 150, "{}", count);
+    }
+
+    #[test]
+    fn test_ties() {
+        // Every piece fits equally well, so each is equally likely
+        let score = |_| 0.0;
+        let context = Context { score: &score };
+
+        let mut randomizer = FriendlyRandomizer::with_seed(Id::variants(), 0);
+        let count = (0..700).filter(|_| randomizer.next_with(&context) == Id::I).count();
+        assert!(count > 70 && count < 130, "{}", count);
+
+        // A piece without a valid score is ranked below every other piece
+        let score = |id| if id == Id::I { f64::NAN } else { 0.0 };
+        let context = Context { score: &score };
+
+        let count = (0..700).filter(|_| randomizer.next_with(&context) == Id::I).count();
+        assert!(count < 70, "{}", count);
+    }
+}
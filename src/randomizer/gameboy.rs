@@ -1,44 +1,84 @@
 //! Implements the Gameboy randomizer.
 
-use std::collections::VecDeque;
-use rand::{self, Rng};
+use rand::Rng;
+use rand::prng::XorShiftRng;
 use block::Id;
-use randomizer::Randomizer;
+use randomizer::{self, Randomizer, Lookahead};
 
 gen_rand!(GameboyRandomizer);
 
 /// A generic memoryless randomizer.
 //
 /// This generates a completely arbitrary sequence of `Id`'s.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameboyRandomizer {
-    /// The lookahead buffer.
-    lookahead: VecDeque<Id>,
+    /// The previewed pieces
+    lookahead: Lookahead,
 
     /// The rng used to generate random values
-    rng: rand::ThreadRng,
+    rng: XorShiftRng,
 
-    /// Last piece id
-    prev: usize
+    /// Index of the last piece
+    prev: usize,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
 }
 
 impl GameboyRandomizer {
     /// Return a new `GameboyRandomizer` instance.
     pub fn new(lookahead: usize) -> GameboyRandomizer {
+        GameboyRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `GameboyRandomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> GameboyRandomizer {
+        GameboyRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `GameboyRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> GameboyRandomizer {
+        assert!(!pieces.is_empty());
+
         let mut gb = GameboyRandomizer {
-            lookahead: VecDeque::with_capacity(lookahead),
-            rng: rand::thread_rng(),
-            prev: 0
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            prev: 0,
+            pieces: pieces.to_vec()
         };
 
-        gb.prev = gb.rng.gen_range(0, Id::variants().len());
+        gb.prev = gb.rng.gen_range(0, pieces.len());
         gb
     }
 
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.pieces)
+    }
+
     fn next_block(&mut self) -> Id {
-        let variants = Id::variants();
-        let roll = 6 * variants.len() - 3;
-        self.prev += ((self.rng.gen_range(0, roll) / 5) + 1) % variants.len();
-        variants[self.prev]
+        let len = self.pieces.len();
+        let roll = 6 * len - 3;
+
+        // The index wraps around the pieces, so a step of `len` is a repeat
+        self.prev = (self.prev + (self.rng.gen_range(0, roll) / 5) + 1) % len;
+        self.pieces[self.prev]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wraps() {
+        for pieces in [Id::variants(), &[Id::I, Id::T][..]].iter() {
+            let mut gb = GameboyRandomizer::with_pieces(0, pieces);
+            for _ in 0..1000 {
+                assert!(pieces.contains(&gb.next()));
+            }
+        }
     }
 }
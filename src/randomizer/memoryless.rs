@@ -1,34 +1,57 @@
 //! Implements a memoryless randomizer.
 
-use std::collections::VecDeque;
-use rand::{self, Rng};
+use rand::Rng;
+use rand::prng::XorShiftRng;
 use block::Id;
-use randomizer::Randomizer;
+use randomizer::{self, Randomizer, Lookahead};
 
 gen_rand!(MemorylessRandomizer);
 
 /// A generic memoryless randomizer.
 //
 /// This generates a completely arbitrary sequence of `Id`'s.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MemorylessRandomizer {
-    /// The lookahead buffer.
-    lookahead: VecDeque<Id>,
+    /// The previewed pieces
+    lookahead: Lookahead,
 
     /// The rng used to generate random values
-    rng: rand::ThreadRng
+    rng: XorShiftRng,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
 }
 
 impl MemorylessRandomizer {
     /// Return a new `MemorylessRandomizer` instance.
     pub fn new(lookahead: usize) -> MemorylessRandomizer {
+        MemorylessRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `MemorylessRandomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> MemorylessRandomizer {
+        MemorylessRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `MemorylessRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> MemorylessRandomizer {
+        assert!(!pieces.is_empty());
+
         MemorylessRandomizer {
-            lookahead: VecDeque::with_capacity(lookahead),
-            rng: rand::thread_rng()
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            pieces: pieces.to_vec()
         }
     }
 
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.pieces)
+    }
+
     fn next_block(&mut self) -> Id {
-        *self.rng.choose(Id::variants()).unwrap()
+        *self.rng.choose(&self.pieces).unwrap()
     }
 }
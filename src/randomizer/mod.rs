@@ -1,8 +1,9 @@
 //! Implements a randomizer.
 //!
 //! Randomizers currently only are required to implement the `Iterator`
-//! trait. Preview pieces are managed by the caller, although this could be
-//! something added as a trait.
+//! trait. Preview pieces are managed by a `Lookahead` queue shared by every
+//! randomizer, which can preview any number of pieces up to the maximum given
+//! on construction.
 //!
 //! It is arguable whether `Randomizer` should imply `Iterator`. A `Randomizer`
 //! is often only processed a single element at a time, and cannot be used in
@@ -10,8 +11,46 @@
 //!
 //! Also, all `Randomizer`'s should return infinite sequences so we can remove
 //! the required `unwrap` on manual calls to `next`.
+//!
+//! The complete state of a randomizer, including its rng, can be saved and
+//! restored. A restored randomizer generates exactly the same sequence.
+//!
+//! Adaptive randomizers choose pieces based on the current game. These are
+//! given a `Context` through `next_with`, and otherwise behave like a
+//! memoryless randomizer. The game decides how a piece is scored, so a
+//! randomizer does not need to know about clear rules or field gravity. A
+//! piece cannot be chosen before the field it is placed on is known, so
+//! adaptive randomizers have no preview.
+//!
+//! ```
+//! use tetrs::import::*;
+//!
+//! let mut bag = randomizer::new("bag", 7).unwrap();
+//! bag.next();
+//!
+//! let state = bag.save();
+//! let expected = (0..14).map(|_| bag.next()).collect::<Vec<_>>();
+//!
+//! let mut restored = randomizer::new("bag", 7).unwrap();
+//! restored.restore(&state).unwrap();
+//! assert_eq!((0..14).map(|_| restored.next()).collect::<Vec<_>>(), expected);
+//! ```
+
+use std::collections::VecDeque;
+use rand::{self, Rng, SeedableRng};
+use rand::prng::XorShiftRng;
 
 use block::Id;
+use piece_set;
+
+/// The state of a game which an adaptive randomizer can react to.
+pub struct Context<'a> {
+    /// Return the score of the best placement of a piece on the field the
+    /// next piece is placed on, where a higher score is better.
+    ///
+    /// If the piece cannot be placed the score is negative infinity.
+    pub score: &'a Fn(Id) -> f64
+}
 
 /// A randomizer must implement an iterator, plus a preview function which
 /// returns a number of lookahead pieces.
@@ -19,42 +58,180 @@ pub trait Randomizer {
     /// Return a vector containing the next `n` pieces that will be retrieved
     /// by the iterator.
     ///
-    /// `n` must be < `lookahead` else a panic will be issued.
-    fn preview(&mut self, lookahead: usize) -> Vec<Id>;
+    /// `n` must be <= `max_preview()` else a panic will be issued.
+    fn preview(&mut self, n: usize) -> Vec<Id>;
+
+    /// Return the maximum number of pieces which can be previewed.
+    fn max_preview(&self) -> usize;
 
     /// Return the next block value in this sequence.
     ///
     /// All sequences should be infinite, and iterator use is limited so we use
     /// a custom function on this trait instead of implementing `Iterator`.
     fn next(&mut self) -> Id;
+
+    /// Return the next block value in this sequence, given the current state
+    /// of the game.
+    ///
+    /// Randomizers which do not adapt to the game ignore `context`.
+    fn next_with(&mut self, context: &Context) -> Id {
+        let _ = context;
+        self.next()
+    }
+
+    /// Return the pieces which could follow the current preview, without
+    /// duplicates.
+    ///
+    /// For a bag randomizer this is the remainder of the current bag.
+    fn possible(&self) -> Vec<Id>;
+
+    /// Return a boxed copy of this randomizer, including its state.
+    fn clone_box(&self) -> Box<Randomizer>;
+
+    /// Return the complete state of this randomizer as JSON.
+    fn save(&self) -> String;
+
+    /// Restore a state returned by `save` on a randomizer of the same kind.
+    ///
+    /// The preview capacity of this randomizer is kept.
+    fn restore(&mut self, state: &str) -> Result<(), String>;
+}
+
+impl Clone for Box<Randomizer> {
+    fn clone(&self) -> Box<Randomizer> {
+        self.clone_box()
+    }
+}
+
+/// The previewed pieces of a randomizer.
+///
+/// Pieces are generated into the queue as they are previewed and taken from
+/// it first by `next`, so a preview is always what is returned next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Lookahead {
+    /// Pieces which have been previewed but not yet returned
+    queue: VecDeque<Id>,
+
+    /// The maximum number of pieces which can be previewed
+    max: usize
+}
+
+impl Lookahead {
+    /// Return a new empty queue which can preview `max` pieces.
+    fn new(max: usize) -> Lookahead {
+        Lookahead { queue: VecDeque::with_capacity(max), max }
+    }
+}
+
+/// Return a random seed for a randomizer.
+fn random_seed() -> u64 {
+    rand::random()
+}
+
+/// Return a new rng determined by `seed`.
+fn new_rng(seed: u64) -> XorShiftRng {
+    XorShiftRng::seed_from_u64(seed)
+}
+
+/// Choose a piece at random with a probability proportional to its weight.
+fn choose_weighted<R: Rng>(rng: &mut R, weights: &[(Id, f64)]) -> Id {
+    let total = weights.iter().map(|&(_, w)| w).sum::<f64>();
+    let mut roll = rng.gen::<f64>() * total;
+
+    for &(id, weight) in weights {
+        if roll < weight {
+            return id;
+        }
+        roll -= weight;
+    }
+
+    // Rounding can leave a tiny remainder, so fall back to the last piece
+    // with any weight.
+    weights.iter().rev().find(|&&(_, w)| w > 0.0).map(|&(id, _)| id).unwrap()
+}
+
+/// Return the unique pieces of `pieces` in sorted order.
+fn unique(pieces: &[Id]) -> Vec<Id> {
+    let mut pieces = pieces.to_vec();
+    pieces.sort();
+    pieces.dedup();
+    pieces
 }
 
 // This macro can be used to generate the `lookahead` and `next` functions for
 // the given randomizer. These are generic across all randomizers but with the
 // lack of inheritance we resort to this method of generation.
+//
+// The state functions require the randomizer to implement `Clone` and serde's
+// traits. A randomizer which does not can provide its own after the name.
+//
+// An adaptive randomizer passes `adaptive` after the name and implements
+// `next_block_with`, which is used in place of `next_block` when a context is
+// given. Adaptive randomizers have no preview, so the queue is never used.
 macro_rules! gen_rand {
     ($id:ident) => {
+        gen_rand!($id, state);
+    };
+
+    ($id:ident, adaptive) => {
+        gen_rand!($id, state
+            fn next_with(&mut self, context: &Context) -> Id {
+                self.next_block_with(context)
+            }
+        );
+    };
+
+    ($id:ident, state $($extra:tt)*) => {
+        gen_rand!($id,
+            fn clone_box(&self) -> Box<Randomizer> {
+                Box::new(self.clone())
+            }
+
+            fn save(&self) -> String {
+                ::serde_json::to_string(self).unwrap()
+            }
+
+            fn restore(&mut self, state: &str) -> Result<(), String> {
+                let max = self.lookahead.max;
+                *self = ::serde_json::from_str(state).map_err(|e| e.to_string())?;
+                self.lookahead.max = max;
+                Ok(())
+            }
+
+            $($extra)*
+        );
+    };
+
+    ($id:ident, $($state:tt)*) => {
         impl Randomizer for $id {
             fn preview(&mut self, amount: usize) -> Vec<Id> {
-                assert!(amount <= self.lookahead.capacity());
+                assert!(amount <= self.lookahead.max,
+                        "cannot preview {} pieces, the maximum is {}", amount, self.lookahead.max);
 
-                if self.lookahead.len() < amount {
+                while self.lookahead.queue.len() < amount {
                     let randvalue = self.next_block();
-                    self.lookahead.push_back(randvalue);
+                    self.lookahead.queue.push_back(randvalue);
                 }
 
-                self.lookahead.iter().cloned().take(amount).collect::<Vec<_>>()
+                self.lookahead.queue.iter().cloned().take(amount).collect::<Vec<_>>()
+            }
+
+            fn max_preview(&self) -> usize {
+                self.lookahead.max
             }
 
             fn next(&mut self) -> Id {
-                if self.lookahead.is_empty() {
-                    self.next_block()
-                }
-                else {
-                    // Always succeeds
-                    self.lookahead.pop_front().unwrap()
+                match self.lookahead.queue.pop_front() {
+                    Some(id) => id,
+                    None => self.next_block()
                 }
             }
+
+            fn possible(&self) -> Vec<Id> {
+                self.possible_blocks()
+            }
+
+            $($state)*
         }
     }
 }
@@ -64,28 +241,125 @@ pub use self::memoryless::MemorylessRandomizer;
 pub use self::gameboy::GameboyRandomizer;
 pub use self::tgm1::TGM1Randomizer;
 pub use self::tgm2::TGM2Randomizer;
+pub use self::tgm3::TGM3Randomizer;
+pub use self::nes::NESRandomizer;
+pub use self::ds::DSRandomizer;
+pub use self::bag_plus_one::BagPlusOneRandomizer;
+pub use self::sequence::{SequenceRandomizer, Exhausted, parse_sequence};
+pub use self::weighted::WeightedRandomizer;
+pub use self::friendly::FriendlyRandomizer;
+pub use self::evil::EvilRandomizer;
 
 mod bag;
 mod memoryless;
 mod gameboy;
 mod tgm1;
 mod tgm2;
+mod tgm3;
+mod nes;
+mod ds;
+mod bag_plus_one;
+mod sequence;
+mod weighted;
+mod friendly;
+mod evil;
+
+pub mod analysis;
 
 /// Factory function for generating randomizers.
 ///
 /// # Names
 ///  - `bag`
+///  - `bag14` (two bags shuffled together)
+///  - `bag7+1` (a bag with one extra random piece)
+///  - `nbag` (a bag of the given pieces, see `with_pieces`)
 ///  - `memoryless`
 ///  - `gameboy`
 ///  - `tgm1`
 ///  - `tgm2`
+///  - `tgm3`
+///  - `nes`
+///  - `ds`
+///  - `sequence:<pieces>` (repeats a fixed sequence, see `parse_sequence`)
+///  - `weighted` (see `WeightedRandomizer`)
+///  - `friendly` (favours pieces which fit the field, has no preview)
+///  - `evil` (chooses the piece which fits the field worst, has no preview)
 pub fn new(name: &str, lookahead: usize) -> Result<Box<Randomizer>, String> {
+    with_pieces(name, lookahead, Id::variants())
+}
+
+/// Factory function for generating randomizers which use the specified
+/// pieces.
+///
+/// See `new` for the available names. A piece may be given more than once,
+/// which for `nbag` places it in each bag more than once and for `weighted`
+/// increases its weight. A fixed sequence ignores `pieces`.
+///
+/// ## Examples
+/// ```
+/// use tetrs::import::*;
+///
+/// let set = piece_set::new("pentomino").unwrap();
+/// let mut randomizer = randomizer::with_pieces("bag", 1, &set.pieces).unwrap();
+/// assert!(set.pieces.contains(&randomizer.next()));
+/// ```
+pub fn with_pieces(name: &str, lookahead: usize, pieces: &[Id])
+        -> Result<Box<Randomizer>, String> {
+    with_seed(name, lookahead, pieces, random_seed())
+}
+
+/// Factory function for generating randomizers which use the specified
+/// pieces and generate a sequence determined by `seed`.
+///
+/// See `new` for the available names. Two randomizers with the same name,
+/// pieces and seed generate the same sequence.
+///
+/// ## Examples
+/// ```
+/// use tetrs::import::*;
+///
+/// let mut a = randomizer::with_seed("tgm2", 1, block::Id::variants(), 42).unwrap();
+/// let mut b = randomizer::with_seed("tgm2", 1, block::Id::variants(), 42).unwrap();
+///
+/// for _ in 0..100 {
+///     assert_eq!(a.next(), b.next());
+/// }
+/// ```
+pub fn with_seed(name: &str, lookahead: usize, pieces: &[Id], seed: u64)
+        -> Result<Box<Randomizer>, String> {
+    if pieces.is_empty() {
+        return Err("a randomizer requires at least one piece".to_string());
+    }
+
     match name {
-        "bag" => Ok(Box::new(BagRandomizer::new(lookahead))),
-        "memoryless" => Ok(Box::new(MemorylessRandomizer::new(lookahead))),
-        "gameboy" => Ok(Box::new(GameboyRandomizer::new(lookahead))),
-        "tgm1" => Ok(Box::new(TGM1Randomizer::new(lookahead))),
-        "tgm2" => Ok(Box::new(TGM2Randomizer::new(lookahead))),
+        "bag" | "nbag" => Ok(Box::new(BagRandomizer::with_seed(lookahead, pieces, seed))),
+        "bag14" => {
+            let bag = pieces.iter().chain(pieces).cloned().collect::<Vec<_>>();
+            Ok(Box::new(BagRandomizer::with_seed(lookahead, &bag, seed)))
+        },
+        "bag7+1" => Ok(Box::new(BagPlusOneRandomizer::with_seed(lookahead, pieces, seed))),
+        "memoryless" => Ok(Box::new(MemorylessRandomizer::with_seed(lookahead, pieces, seed))),
+        "gameboy" => Ok(Box::new(GameboyRandomizer::with_seed(lookahead, pieces, seed))),
+        "tgm1" => Ok(Box::new(TGM1Randomizer::with_seed(lookahead, pieces, seed))),
+        "tgm2" => Ok(Box::new(TGM2Randomizer::with_seed(lookahead, pieces, seed))),
+        "tgm3" => Ok(Box::new(TGM3Randomizer::with_seed(lookahead, pieces, seed))),
+        "nes" => Ok(Box::new(NESRandomizer::with_seed(lookahead, pieces, seed))),
+        "ds" => Ok(Box::new(DSRandomizer::with_seed(lookahead, pieces, seed))),
+        "weighted" => Ok(Box::new(WeightedRandomizer::with_seed(lookahead, pieces, seed))),
+        "friendly" => Ok(Box::new(FriendlyRandomizer::with_seed(pieces, seed))),
+        "evil" => Ok(Box::new(EvilRandomizer::with_seed(pieces, seed))),
+        _ if name.starts_with("sequence:") => {
+            let sequence = parse_sequence(&name["sequence:".len()..])?;
+            Ok(Box::new(SequenceRandomizer::new(lookahead, &sequence, Exhausted::Repeat)))
+        },
         _ => Err(format!("unknown randomizer: {}", name))
     }
 }
+
+/// Factory function for generating randomizers which use the named piece set.
+///
+/// See `piece_set::new` for the available piece set names.
+pub fn with_piece_set(name: &str, lookahead: usize, piece_set: &str)
+        -> Result<Box<Randomizer>, String> {
+    with_pieces(name, lookahead, &piece_set::new(piece_set)?.pieces)
+}
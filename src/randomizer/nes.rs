@@ -0,0 +1,95 @@
+//! Implements the NES randomizer.
+
+use rand::Rng;
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead};
+
+gen_rand!(NESRandomizer);
+
+/// A NES Tetris randomizer.
+///
+/// An 8-sided die is rolled, where the extra side is a reroll. If the roll
+/// is the extra side or repeats the previous piece, a single 7-sided reroll
+/// is taken instead. This roughly quarters the chance of a repeat.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NESRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// The last piece generated
+    prev: Id,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
+}
+
+impl NESRandomizer {
+    /// Return a new `NESRandomizer` instance.
+    pub fn new(lookahead: usize) -> NESRandomizer {
+        NESRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `NESRandomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> NESRandomizer {
+        NESRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `NESRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> NESRandomizer {
+        assert!(!pieces.is_empty());
+
+        NESRandomizer {
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            prev: Id::None,
+            pieces: pieces.to_vec()
+        }
+    }
+
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.pieces)
+    }
+
+    fn next_block(&mut self) -> Id {
+        let roll = self.rng.gen_range(0, self.pieces.len() + 1);
+
+        let piece = match self.pieces.get(roll) {
+            Some(&piece) if piece != self.prev => piece,
+            _ => *self.rng.choose(&self.pieces).unwrap()
+        };
+
+        self.prev = piece;
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomizer::Randomizer;
+
+    #[test]
+    fn test_repeats() {
+        let mut randomizer = NESRandomizer::with_seed(1, Id::variants(), 0);
+        let mut prev = randomizer.next();
+        let mut repeats = 0;
+
+        for _ in 0..10000 {
+            let piece = randomizer.next();
+            if piece == prev {
+                repeats += 1;
+            }
+            prev = piece;
+        }
+
+        // A repeat requires a reroll onto the same piece: 2/8 * 1/7
+        assert!(repeats > 250 && repeats < 470, "{}", repeats);
+    }
+}
@@ -0,0 +1,205 @@
+//! Implements a randomizer which replays a fixed sequence.
+
+use block::Id;
+use randomizer::{BagRandomizer, Randomizer, Lookahead, Context};
+
+/// What a `SequenceRandomizer` does once its sequence is exhausted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Exhausted {
+    /// Play the sequence again from the start
+    Repeat,
+
+    /// Draw pieces from the fallback randomizer
+    Fallback,
+
+    /// Return `Id::None`, which ends the game when spawned
+    End
+}
+
+/// A randomizer which plays a fixed sequence of pieces.
+///
+/// This is useful for puzzles, opener practice and tests.
+///
+/// ```
+/// use tetrs::import::*;
+/// use tetrs::randomizer::{SequenceRandomizer, Exhausted};
+///
+/// let sequence = randomizer::parse_sequence("TIO").unwrap();
+/// let mut randomizer = SequenceRandomizer::new(1, &sequence, Exhausted::End);
+///
+/// assert_eq!(randomizer.next(), block::Id::T);
+/// assert_eq!(randomizer.preview(1), vec![block::Id::I]);
+/// assert_eq!(randomizer.next(), block::Id::I);
+/// assert_eq!(randomizer.next(), block::Id::O);
+/// assert_eq!(randomizer.next(), block::Id::None);
+/// ```
+#[derive(Clone)]
+pub struct SequenceRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The sequence to play
+    sequence: Vec<Id>,
+
+    /// The index of the next piece in the sequence
+    index: usize,
+
+    /// What happens once the sequence is exhausted
+    exhausted: Exhausted,
+
+    /// The randomizer used after the sequence with `Exhausted::Fallback`.
+    /// This is a `BagRandomizer` by default.
+    pub fallback: Box<Randomizer>
+}
+
+impl SequenceRandomizer {
+    /// Return a new `SequenceRandomizer` instance.
+    pub fn new(lookahead: usize, sequence: &[Id], exhausted: Exhausted) -> SequenceRandomizer {
+        SequenceRandomizer {
+            lookahead: Lookahead::new(lookahead),
+            sequence: sequence.to_vec(),
+            index: 0,
+            exhausted,
+            fallback: Box::new(BagRandomizer::new(lookahead))
+        }
+    }
+
+    /// Return a new `SequenceRandomizer` instance which continues with
+    /// `fallback` once the sequence is exhausted.
+    pub fn with_fallback(lookahead: usize, sequence: &[Id], fallback: Box<Randomizer>)
+            -> SequenceRandomizer {
+        SequenceRandomizer {
+            fallback, ..SequenceRandomizer::new(lookahead, sequence, Exhausted::Fallback)
+        }
+    }
+
+    fn next_block(&mut self) -> Id {
+        if self.index == self.sequence.len() {
+            match self.exhausted {
+                Exhausted::Repeat if !self.sequence.is_empty() => self.index = 0,
+                Exhausted::Fallback => return self.fallback.next(),
+                _ => return Id::None
+            }
+        }
+
+        self.index += 1;
+        self.sequence[self.index - 1]
+    }
+
+    /// Return the pieces which can be generated next.
+    fn possible_blocks(&self) -> Vec<Id> {
+        if self.index < self.sequence.len() {
+            return vec![self.sequence[self.index]];
+        }
+
+        match self.exhausted {
+            Exhausted::Repeat if !self.sequence.is_empty() => vec![self.sequence[0]],
+            Exhausted::Fallback => self.fallback.possible(),
+            _ => vec![Id::None]
+        }
+    }
+}
+
+/// The saved state of a `SequenceRandomizer`.
+///
+/// The fallback randomizer is saved as a nested state, since its kind is not
+/// known when restoring.
+#[derive(Serialize, Deserialize)]
+struct State {
+    lookahead: Vec<Id>,
+    sequence: Vec<Id>,
+    index: usize,
+    exhausted: Exhausted,
+    fallback: String
+}
+
+gen_rand!(SequenceRandomizer,
+    fn clone_box(&self) -> Box<Randomizer> {
+        Box::new(self.clone())
+    }
+
+    fn save(&self) -> String {
+        ::serde_json::to_string(&State {
+            lookahead: self.lookahead.queue.iter().cloned().collect(),
+            sequence: self.sequence.clone(),
+            index: self.index,
+            exhausted: self.exhausted,
+            fallback: self.fallback.save()
+        }).unwrap()
+    }
+
+    fn restore(&mut self, state: &str) -> Result<(), String> {
+        let state: State = ::serde_json::from_str(state).map_err(|e| e.to_string())?;
+
+        self.fallback.restore(&state.fallback)?;
+        self.lookahead.queue = state.lookahead.into_iter().collect();
+        self.sequence = state.sequence;
+        self.index = state.index;
+        self.exhausted = state.exhausted;
+        Ok(())
+    }
+
+    fn next_with(&mut self, context: &Context) -> Id {
+        let fallback = self.index == self.sequence.len() && self.exhausted == Exhausted::Fallback;
+
+        match self.lookahead.queue.pop_front() {
+            Some(id) => id,
+            None if fallback => self.fallback.next_with(context),
+            None => self.next_block()
+        }
+    }
+);
+
+/// Parse a sequence of pieces.
+///
+/// A sequence containing a comma is a list of piece names (e.g.
+/// `"I5, X, T"`), otherwise every character is a piece name (e.g.
+/// `"IJLOSTZ"`). Whitespace is ignored.
+pub fn parse_sequence(sequence: &str) -> Result<Vec<Id>, String> {
+    let names = if sequence.contains(',') {
+        sequence.split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>()
+    }
+    else {
+        sequence.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_string()).collect()
+    };
+
+    names.iter()
+         .map(|name| Id::from_name(name).ok_or(format!("unknown piece: {}", name)))
+         .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomizer::Randomizer;
+
+    #[test]
+    fn test_exhausted() {
+        let sequence = parse_sequence("SZ").unwrap();
+
+        let mut randomizer = SequenceRandomizer::new(1, &sequence, Exhausted::Repeat);
+        let pieces = (0..5).map(|_| randomizer.next()).collect::<Vec<_>>();
+        assert_eq!(pieces, vec![Id::S, Id::Z, Id::S, Id::Z, Id::S]);
+
+        let mut randomizer = SequenceRandomizer::with_fallback(
+            1, &sequence, Box::new(SequenceRandomizer::new(1, &[Id::O], Exhausted::Repeat))
+        );
+        let pieces = (0..4).map(|_| randomizer.next()).collect::<Vec<_>>();
+        assert_eq!(pieces, vec![Id::S, Id::Z, Id::O, Id::O]);
+
+        // The fallback is saved with the sequence
+        let mut randomizer = SequenceRandomizer::new(1, &sequence, Exhausted::Fallback);
+        randomizer.next();
+        let state = randomizer.save();
+        let expected = (0..8).map(|_| randomizer.next()).collect::<Vec<_>>();
+
+        let mut restored = SequenceRandomizer::new(1, &[], Exhausted::End);
+        restored.restore(&state).unwrap();
+        assert_eq!(restored.possible(), vec![Id::Z]);
+        assert_eq!((0..8).map(|_| restored.next()).collect::<Vec<_>>(), expected);
+
+        assert_eq!(parse_sequence("I5, X").unwrap(),
+                   vec![Id::from_name("I5").unwrap(), Id::from_name("X").unwrap()]);
+        assert!(parse_sequence("IQ").is_err());
+    }
+}
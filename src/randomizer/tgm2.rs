@@ -1,22 +1,22 @@
 //! Implements the TGM2 randomizer
 
-use std::collections::VecDeque;
-use rand::{self, Rng};
+use rand::Rng;
+use rand::prng::XorShiftRng;
 use block::Id;
-use randomizer::Randomizer;
+use randomizer::{self, Randomizer, Lookahead};
 
 gen_rand!(TGM2Randomizer);
 
 /// A TGM2 randomizer.
 //
 /// This generates a completely arbitrary sequence of `Id`'s.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TGM2Randomizer {
-    /// The lookahead buffer.
-    lookahead: VecDeque<Id>,
+    /// The previewed pieces
+    lookahead: Lookahead,
 
     /// The rng used to generate random values
-    rng: rand::ThreadRng,
+    rng: XorShiftRng,
 
     /// History of blocks
     history: [Id; 4],
@@ -26,27 +26,50 @@ pub struct TGM2Randomizer {
 
     /// Is this the first piece?
     first: bool,
+
+    /// The pieces to choose from
+    pieces: Vec<Id>
 }
 
 impl TGM2Randomizer {
     /// Return a new `TGM2Randomizer` instance.
     pub fn new(lookahead: usize) -> TGM2Randomizer {
+        TGM2Randomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `TGM2Randomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> TGM2Randomizer {
+        TGM2Randomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `TGM2Randomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> TGM2Randomizer {
+        assert!(!pieces.is_empty());
+
         TGM2Randomizer {
-            lookahead: VecDeque::with_capacity(lookahead),
-            rng: rand::thread_rng(),
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
             history: [Id::S, Id::Z, Id::S, Id::Z],
             rolls: 6,
-            first: true
+            first: true,
+            pieces: pieces.to_vec()
         }
     }
 
+    /// Return the pieces which can be generated next, which is every piece.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.pieces)
+    }
+
     fn next_block(&mut self) -> Id {
         let mut piece = Id::None;
 
         if self.first {
             const SZO: [Id; 3] = [Id::S, Id::Z, Id::O];
             for _ in 0..self.rolls {
-                piece = *self.rng.choose(Id::variants()).unwrap();
+                piece = *self.rng.choose(&self.pieces).unwrap();
                 if !SZO.contains(&piece) {
                     break;
                 }
@@ -54,9 +77,11 @@ impl TGM2Randomizer {
             self.first = false;
         }
         else {
-            loop {
+            // Like the first piece, at most `rolls` attempts are made to
+            // avoid the history and the last roll is kept
+            for _ in 0..self.rolls {
                 // Generate a random piece and check if it is in history
-                piece = *self.rng.choose(Id::variants()).unwrap();
+                piece = *self.rng.choose(&self.pieces).unwrap();
                 if !self.history.contains(&piece) {
                     break;
                 }
@@ -70,3 +95,18 @@ impl TGM2Randomizer {
         piece
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_piece_set() {
+        // Every piece is always in the history, so rolling must give up
+        let pieces = [Id::I, Id::T];
+        let mut rd = TGM2Randomizer::with_pieces(0, &pieces);
+        for _ in 0..100 {
+            assert!(pieces.contains(&rd.next()));
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Implements the TGM3 randomizer
+
+use rand::Rng;
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead};
+
+gen_rand!(TGM3Randomizer);
+
+/// A TGM3 randomizer.
+///
+/// Pieces are drawn from a pool containing 5 copies of each piece, using the
+/// same 4-piece history and 6 rolls as TGM2. Each time a piece is drawn, its
+/// place in the pool is given to the piece which has gone the longest without
+/// being seen, so droughts are much shorter than with TGM2.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TGM3Randomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// History of blocks
+    history: [Id; 4],
+
+    /// How many rolls are performed per iteration
+    rolls: usize,
+
+    /// Is this the first piece?
+    first: bool,
+
+    /// The pool pieces are drawn from
+    pool: Vec<Id>,
+
+    /// Pieces ordered from least to most recently seen
+    order: Vec<Id>
+}
+
+impl TGM3Randomizer {
+    /// Return a new `TGM3Randomizer` instance.
+    pub fn new(lookahead: usize) -> TGM3Randomizer {
+        TGM3Randomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `TGM3Randomizer` instance using the specified pieces.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> TGM3Randomizer {
+        TGM3Randomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `TGM3Randomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> TGM3Randomizer {
+        assert!(!pieces.is_empty());
+
+        TGM3Randomizer {
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            history: [Id::S, Id::Z, Id::S, Id::Z],
+            rolls: 6,
+            first: true,
+            pool: pieces.iter().flat_map(|&id| vec![id; 5]).collect(),
+            order: pieces.to_vec()
+        }
+    }
+
+    /// Return the pieces which can be generated next.
+    fn possible_blocks(&self) -> Vec<Id> {
+        const SZO: [Id; 3] = [Id::S, Id::Z, Id::O];
+
+        if self.first && self.order.iter().any(|id| !SZO.contains(id)) {
+            randomizer::unique(&self.order.iter().cloned()
+                                   .filter(|id| !SZO.contains(id))
+                                   .collect::<Vec<_>>())
+        }
+        else {
+            randomizer::unique(&self.pool)
+        }
+    }
+
+    fn next_block(&mut self) -> Id {
+        let mut piece = Id::None;
+
+        if self.first {
+            // The first piece is never an S, Z or O, and does not affect the
+            // pool.
+            const SZO: [Id; 3] = [Id::S, Id::Z, Id::O];
+            let first = self.order.iter().cloned()
+                            .filter(|id| !SZO.contains(id))
+                            .collect::<Vec<_>>();
+
+            piece = *self.rng.choose(&first).unwrap_or(&self.order[0]);
+            self.first = false;
+        }
+        else {
+            let mut index = 0;
+
+            for roll in 0..self.rolls {
+                // Generate a random piece and check if it is in history
+                index = self.rng.gen_range(0, self.pool.len());
+                piece = self.pool[index];
+                if !self.history.contains(&piece) {
+                    break;
+                }
+
+                // Bias later rolls towards the most droughted piece
+                if roll + 1 < self.rolls {
+                    self.pool[index] = self.order[0];
+                }
+            }
+
+            if let Some(position) = self.order.iter().position(|&id| id == piece) {
+                self.order.remove(position);
+            }
+            self.order.push(piece);
+            self.pool[index] = self.order[0];
+        }
+
+        for i in (1..self.history.len()).rev() {
+            self.history[i] = self.history[i - 1];
+        }
+        self.history[0] = piece;
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomizer::{Randomizer, MemorylessRandomizer};
+
+    /// Return the repeat ratio and longest drought of a sequence.
+    fn stats(randomizer: &mut Randomizer, n: usize) -> (f64, usize) {
+        let mut last_seen = vec![0; 7];
+        let mut longest = 0;
+        let mut repeats = 0;
+        let mut prev = Id::None;
+
+        for i in 1..n + 1 {
+            let piece = randomizer.next();
+            let index = Id::variants().iter().position(|&id| id == piece).unwrap();
+
+            longest = longest.max(i - last_seen[index]);
+            last_seen[index] = i;
+
+            if piece == prev {
+                repeats += 1;
+            }
+            prev = piece;
+        }
+
+        (repeats as f64 / n as f64, longest)
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut randomizer = TGM3Randomizer::with_seed(1, Id::variants(), 0);
+
+        // The first piece is never an S, Z or O
+        assert!(![Id::S, Id::Z, Id::O].contains(&randomizer.next()));
+
+        // Every piece is drawn equally often
+        let mut counts = vec![0; 7];
+        for _ in 0..7000 {
+            let piece = randomizer.next();
+            counts[Id::variants().iter().position(|&id| id == piece).unwrap()] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 800 && c < 1200), "{:?}", counts);
+    }
+
+    #[test]
+    fn test_drought() {
+        let mut randomizer = TGM3Randomizer::with_seed(1, Id::variants(), 0);
+        let mut memoryless = MemorylessRandomizer::with_seed(1, Id::variants(), 0);
+
+        let (repeats, longest) = stats(&mut randomizer, 10000);
+        let (memoryless_repeats, memoryless_longest) = stats(&mut memoryless, 10000);
+
+        // Immediate repeats are rare and droughts are short
+        assert!(repeats < 0.02, "{}", repeats);
+        assert!(repeats < memoryless_repeats / 5.0, "{} {}", repeats, memoryless_repeats);
+        assert!(longest < 30, "{}", longest);
+        assert!(longest < memoryless_longest, "{} {}", longest, memoryless_longest);
+    }
+}
@@ -0,0 +1,158 @@
+//! Implements a weighted randomizer.
+
+use rand::prng::XorShiftRng;
+use block::Id;
+use randomizer::{self, Randomizer, Lookahead};
+
+gen_rand!(WeightedRandomizer);
+
+/// A memoryless randomizer with a configurable weight per piece.
+///
+/// Each piece is generated with a probability proportional to its weight.
+///
+/// ```
+/// use tetrs::import::*;
+/// use tetrs::randomizer::WeightedRandomizer;
+///
+/// // Never generate an S or Z, and an I twice as often as anything else
+/// let weights = [(block::Id::I, 2.0), (block::Id::T, 1.0), (block::Id::S, 0.0),
+///                (block::Id::Z, 0.0), (block::Id::O, 1.0)];
+/// let mut randomizer = WeightedRandomizer::with_weights(1, &weights);
+///
+/// assert!(randomizer.possible().iter().all(|&id| id != block::Id::S));
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeightedRandomizer {
+    /// The previewed pieces
+    lookahead: Lookahead,
+
+    /// The rng used to generate random values
+    rng: XorShiftRng,
+
+    /// The pieces to choose from and their relative weights
+    weights: Vec<(Id, f64)>,
+
+    /// Is this the first piece?
+    first: bool,
+
+    /// Should the first piece never be an S or Z?
+    pub no_sz_first: bool
+}
+
+impl WeightedRandomizer {
+    /// Return a new `WeightedRandomizer` instance which weights every piece
+    /// equally.
+    pub fn new(lookahead: usize) -> WeightedRandomizer {
+        WeightedRandomizer::with_pieces(lookahead, Id::variants())
+    }
+
+    /// Return a new `WeightedRandomizer` instance using the specified pieces.
+    ///
+    /// Each piece has a weight of the number of times it is given.
+    pub fn with_pieces(lookahead: usize, pieces: &[Id]) -> WeightedRandomizer {
+        WeightedRandomizer::with_seed(lookahead, pieces, randomizer::random_seed())
+    }
+
+    /// Return a new `WeightedRandomizer` instance using the specified pieces.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_seed(lookahead: usize, pieces: &[Id], seed: u64) -> WeightedRandomizer {
+        assert!(!pieces.is_empty());
+
+        let weights = randomizer::unique(pieces).into_iter().map(|id| {
+            (id, pieces.iter().filter(|&&p| p == id).count() as f64)
+        });
+
+        WeightedRandomizer::with_weights_and_seed(lookahead, &weights.collect::<Vec<_>>(), seed)
+    }
+
+    /// Return a new `WeightedRandomizer` instance with the specified weights.
+    ///
+    /// At least one weight must be positive and none may be negative.
+    pub fn with_weights(lookahead: usize, weights: &[(Id, f64)]) -> WeightedRandomizer {
+        WeightedRandomizer::with_weights_and_seed(lookahead, weights, randomizer::random_seed())
+    }
+
+    /// Return a new `WeightedRandomizer` instance with the specified weights.
+    ///
+    /// The generated sequence is determined by `seed`.
+    pub fn with_weights_and_seed(lookahead: usize, weights: &[(Id, f64)], seed: u64)
+            -> WeightedRandomizer {
+        assert!(weights.iter().all(|&(_, w)| w >= 0.0));
+        assert!(weights.iter().any(|&(_, w)| w > 0.0));
+
+        WeightedRandomizer {
+            lookahead: Lookahead::new(lookahead),
+            rng: randomizer::new_rng(seed),
+            weights: weights.to_vec(),
+            first: true,
+            no_sz_first: false
+        }
+    }
+
+    /// Return the weights used for the next piece.
+    fn current_weights(&self) -> Vec<(Id, f64)> {
+        const SZ: [Id; 2] = [Id::S, Id::Z];
+
+        let weights = self.weights.iter().cloned()
+                          .filter(|&(id, w)| w > 0.0 && !(self.first && self.no_sz_first &&
+                                                          SZ.contains(&id)))
+                          .collect::<Vec<_>>();
+
+        // Only S and Z pieces can be generated so they cannot be avoided
+        if weights.is_empty() {
+            self.weights.iter().cloned().filter(|&(_, w)| w > 0.0).collect()
+        }
+        else {
+            weights
+        }
+    }
+
+    /// Return the pieces which can be generated next.
+    fn possible_blocks(&self) -> Vec<Id> {
+        randomizer::unique(&self.current_weights().into_iter()
+                               .map(|(id, _)| id)
+                               .collect::<Vec<_>>())
+    }
+
+    fn next_block(&mut self) -> Id {
+        let weights = self.current_weights();
+        self.first = false;
+        randomizer::choose_weighted(&mut self.rng, &weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights() {
+        let weights = [(Id::I, 3.0), (Id::T, 1.0), (Id::O, 0.0)];
+        let mut randomizer = WeightedRandomizer::with_weights_and_seed(0, &weights, 7);
+
+        let pieces = (0..10000).map(|_| randomizer.next()).collect::<Vec<_>>();
+        let count = pieces.iter().filter(|&&id| id == Id::I).count();
+
+        assert!(count > 7000 && count < 8000, "{}", count);
+        assert!(!pieces.contains(&Id::O));
+
+        // Duplicate pieces are weighted by their count
+        let randomizer = WeightedRandomizer::with_seed(0, &[Id::S, Id::S, Id::T], 0);
+        assert_eq!(randomizer.weights, vec![(Id::T, 1.0), (Id::S, 2.0)]);
+    }
+
+    #[test]
+    fn test_no_sz_first() {
+        for seed in 0..100 {
+            let mut randomizer = WeightedRandomizer::with_seed(0, &[Id::S, Id::Z, Id::T], seed);
+            randomizer.no_sz_first = true;
+            assert_eq!(randomizer.possible(), vec![Id::T]);
+            assert_eq!(randomizer.next(), Id::T);
+        }
+
+        let mut randomizer = WeightedRandomizer::with_seed(0, &[Id::S, Id::Z], 0);
+        randomizer.no_sz_first = true;
+        assert!(randomizer.next() != Id::None);
+    }
+}
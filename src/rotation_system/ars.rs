@@ -2,6 +2,12 @@
 
 rs_gen!(ARS);
 
+/// Pieces spawn centred at the top of the visible field, rounding left, in
+/// their flat-side up orientation.
+fn spawn_rule(_id: Id) -> SpawnRule {
+    SpawnRule { centre: Some(Centre::Left), row: Some(-1), ..Default::default() }
+}
+
 static I: [[(usize, usize); 4]; 4] = [
     [(0, 1), (1, 1), (2, 1), (3, 1)],
     [(2, 0), (2, 1), (2, 2), (2, 3)],
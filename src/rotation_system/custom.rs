@@ -0,0 +1,252 @@
+//! A rotation system which is defined at runtime.
+//!
+//! A definition lists the offsets of each piece in every rotation, using the
+//! same layout as the built-in rotation systems. The spawn offset, spawn
+//! rotation and colour of each piece are optional, as are the spawn row,
+//! whether pieces are centred on the field and whether pieces drop one row on
+//! spawn.
+//!
+//! ```text
+//! {
+//!   "pieces": {
+//!     "I": {
+//!       "rotations": [
+//!         [[0, 1], [1, 1], [2, 1], [3, 1]],
+//!         [[2, 0], [2, 1], [2, 2], [2, 3]],
+//!         [[0, 2], [1, 2], [2, 2], [3, 2]],
+//!         [[1, 0], [1, 1], [1, 2], [1, 3]]
+//!       ],
+//!       "spawn_offset": [1, 0],
+//!       "spawn_rotation": "R0",
+//!       "colour": [0, 255, 255]
+//!     },
+//!     ...
+//!   },
+//!   "spawn_row": -2,
+//!   "spawn_centre": "Left",
+//!   "spawn_drop": true
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use serde_json;
+
+use block::{Id, Rotation};
+use piece_set;
+use rotation_system::{RotationSystem, SpawnRule, Centre};
+
+/// The definition of a single piece.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PieceDefinition {
+    /// The offsets of the piece in each of the four rotations
+    pub rotations: Vec<Vec<(usize, usize)>>,
+
+    /// The offset applied to the field spawn
+    #[serde(default)]
+    pub spawn_offset: (i32, i32),
+
+    /// The initial orientation of the piece
+    #[serde(default)]
+    pub spawn_rotation: Rotation,
+
+    /// The `(r, g, b)` colour of the piece
+    #[serde(default)]
+    pub colour: Option<(u8, u8, u8)>
+}
+
+/// The serialized form of a `Custom` rotation system.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Definition {
+    /// The definition of each piece, keyed by name (e.g. `"T"`)
+    pub pieces: BTreeMap<String, PieceDefinition>,
+
+    /// The spawn row relative to the first visible row
+    #[serde(default)]
+    pub spawn_row: Option<i32>,
+
+    /// The side pieces round towards when centred on the field, if pieces
+    /// are centred
+    #[serde(default)]
+    pub spawn_centre: Option<Centre>,
+
+    /// Should pieces be moved down one row on spawn if unobstructed?
+    #[serde(default)]
+    pub spawn_drop: bool
+}
+
+/// A rotation system constructed from a `Definition`.
+pub struct Custom {
+    pieces: HashMap<Id, PieceDefinition>,
+    spawn_row: Option<i32>,
+    spawn_centre: Option<Centre>,
+    spawn_drop: bool
+}
+
+impl Custom {
+    /// Construct a rotation system from a definition.
+    ///
+    /// A piece which is not in the piece table is registered using its spawn
+    /// rotation as its shape. An error is returned if there are no pieces, or
+    /// if a piece does not have four non-empty rotations.
+    pub fn from_definition(definition: Definition) -> Result<Custom, String> {
+        if definition.pieces.is_empty() {
+            return Err("a rotation system requires at least one piece".to_string());
+        }
+
+        // Registering a piece cannot be undone, so every piece is checked
+        // first and a definition which fails leaves the piece table as it was
+        for (name, piece) in &definition.pieces {
+            if name.is_empty() {
+                return Err("a piece requires a name".to_string());
+            }
+
+            if piece.rotations.len() != 4 || piece.rotations.iter().any(|r| r.is_empty()) {
+                return Err(format!("piece {} must have four non-empty rotations", name));
+            }
+        }
+
+        let mut pieces = HashMap::new();
+
+        for (name, piece) in definition.pieces {
+            let id = match Id::from_name(&name) {
+                Some(id) => id,
+                None => piece_set::register(&name, &piece.rotations[0],
+                                            piece.colour.unwrap_or((128, 128, 128)))?
+            };
+
+            pieces.insert(id, piece);
+        }
+
+        Ok(Custom {
+            pieces,
+            spawn_row: definition.spawn_row,
+            spawn_centre: definition.spawn_centre,
+            spawn_drop: definition.spawn_drop
+        })
+    }
+
+    /// Construct a rotation system from a JSON definition.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::rotation_system::Custom;
+    ///
+    /// let rs = Custom::from_json(r#"{
+    ///     "pieces": {
+    ///         "dot": { "rotations": [[[0, 0]], [[0, 0]], [[0, 0]], [[0, 0]]] }
+    ///     }
+    /// }"#).unwrap();
+    ///
+    /// let dot = block::Id::from_name("dot").unwrap();
+    /// assert_eq!(rs.pieces(), vec![dot]);
+    /// assert_eq!(rs.data(dot, Rotation::R90), &[(0, 0)]);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Custom, String> {
+        let definition = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Custom::from_definition(definition)
+    }
+
+    /// Load a rotation system from a JSON definition file.
+    pub fn from_file(filename: &str) -> Result<Custom, String> {
+        let mut s = String::new();
+        File::open(filename).and_then(|mut f| f.read_to_string(&mut s))
+                            .map_err(|e| format!("{}: {}", filename, e))?;
+        Custom::from_json(&s)
+    }
+}
+
+impl RotationSystem for Custom {
+    fn data(&self, ty: Id, rotation: Rotation) -> &[(usize, usize)] {
+        match self.pieces.get(&ty) {
+            Some(piece) => &piece.rotations[rotation as usize],
+            None => panic!("Attempted to get data for Id: {:?}", ty)
+        }
+    }
+
+    fn pieces(&self) -> Vec<Id> {
+        let mut pieces = self.pieces.keys().cloned().collect::<Vec<_>>();
+        pieces.sort();
+        pieces
+    }
+
+    fn spawn_rule(&self, id: Id) -> SpawnRule {
+        let piece = self.pieces.get(&id);
+
+        SpawnRule {
+            offset: piece.map_or((0, 0), |piece| piece.spawn_offset),
+            rotation: piece.map_or(Rotation::R0, |piece| piece.spawn_rotation),
+            centre: self.spawn_centre,
+            row: self.spawn_row,
+            drop: self.spawn_drop
+        }
+    }
+
+    fn colour(&self, id: Id) -> Option<(u8, u8, u8)> {
+        self.pieces.get(&id).and_then(|piece| piece.colour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rotation_system::SRS;
+
+    /// Return the definition of an existing rotation system.
+    fn definition(rs: &RotationSystem) -> Definition {
+        let pieces = Id::variants().iter().map(|&id| {
+            let rotations = [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270]
+                                .iter()
+                                .map(|&r| rs.data(id, r).to_vec())
+                                .collect();
+
+            (format!("{:?}", id), PieceDefinition {
+                rotations, spawn_offset: (0, 0), spawn_rotation: Rotation::R0, colour: None
+            })
+        });
+
+        Definition {
+            pieces: pieces.collect(), spawn_row: None, spawn_centre: None, spawn_drop: false
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        let srs = SRS::new();
+        let mut definition = definition(&*srs);
+        definition.pieces.get_mut("T").unwrap().colour = Some((128, 0, 128));
+
+        let json = serde_json::to_string(&definition).unwrap();
+        let rs = Custom::from_json(&json).unwrap();
+
+        for &id in Id::variants() {
+            assert_eq!(rs.data(id, Rotation::R90), srs.data(id, Rotation::R90));
+        }
+        assert_eq!(rs.colour(Id::T), Some((128, 0, 128)));
+        assert_eq!(rs.colour(Id::I), None);
+
+        definition.pieces.get_mut("I").unwrap().rotations.pop();
+        assert!(Custom::from_definition(definition.clone()).is_err());
+
+        // Unknown pieces are added to the piece table
+        definition.pieces.remove("I");
+        definition.pieces.insert("T'".to_string(), definition.pieces["T"].clone());
+        let rs = Custom::from_definition(definition).unwrap();
+        let id = Id::from_name("T'").unwrap();
+        assert_eq!(rs.pieces().len(), 7);
+        assert_eq!(rs.data(id, Rotation::R0), srs.data(Id::T, Rotation::R0));
+
+        assert!(Custom::from_json(r#"{ "pieces": {} }"#).is_err());
+
+        // Nothing is registered if any piece is invalid
+        assert!(Custom::from_json(r#"{
+            "pieces": {
+                "unused1": { "rotations": [[[0, 0]], [[0, 0]], [[0, 0]], [[0, 0]]] },
+                "unused2": { "rotations": [[[0, 0]]] }
+            }
+        }"#).is_err());
+        assert_eq!(Id::from_name("unused1"), None);
+    }
+}
@@ -2,6 +2,11 @@
 
 rs_gen!(DTET);
 
+/// Pieces spawn centred at the top of the visible field, rounding left.
+fn spawn_rule(_id: Id) -> SpawnRule {
+    SpawnRule { centre: Some(Centre::Left), row: Some(-1), ..Default::default() }
+}
+
 static I: [[(usize, usize); 4]; 4] = [
     [(0, 2), (1, 2), (2, 2), (3, 2)],
     [(2, 0), (2, 1), (2, 2), (2, 3)],
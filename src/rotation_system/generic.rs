@@ -0,0 +1,98 @@
+//! A rotation system for arbitrary pieces.
+//!
+//! Each piece in the piece table is rotated clockwise about the center of its
+//! bounding square. The spawn orientation is the shape given in the piece
+//! table. This matches SRS for every tetromino but the O, which is not offset
+//! from the left of its bounding box.
+//!
+//! Pieces spawn centred on a 4-wide bounding square, so tetrominoes spawn at
+//! the field spawn and wider pieces spawn further left.
+
+use std::sync::Arc;
+
+use block::{Id, Rotation};
+use piece_set;
+use rotation_system::{RotationSystem, SpawnRule};
+
+/// A rotation system which rotates each piece within its bounding square.
+///
+/// Offsets are computed on construction, so a piece registered afterwards
+/// requires a new instance.
+pub struct Generic {
+    data: Vec<[Vec<(usize, usize)>; 4]>
+}
+
+/// Rotate a shape clockwise within its bounding square.
+fn rotate(shape: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let n = shape.iter().map(|&(x, y)| x.max(y)).max().unwrap_or(0) + 1;
+
+    let mut rotated = shape.iter().map(|&(x, y)| (n - 1 - y, x)).collect::<Vec<_>>();
+    rotated.sort_by_key(|&(x, y)| (y, x));
+    rotated
+}
+
+impl Generic {
+    /// Return a new instance
+    pub fn new() -> Arc<Generic> {
+        let data = (0..piece_set::count()).map(|i| {
+            let mut r0 = Id::from(i).shape().to_vec();
+            r0.sort_by_key(|&(x, y)| (y, x));
+
+            let r90 = rotate(&r0);
+            let r180 = rotate(&r90);
+            let r270 = rotate(&r180);
+            [r0, r90, r180, r270]
+        });
+
+        Arc::new(Generic { data: data.collect() })
+    }
+}
+
+impl RotationSystem for Generic {
+    fn data(&self, ty: Id, rotation: Rotation) -> &[(usize, usize)] {
+        match self.data.get(ty.index()) {
+            Some(rotations) => &rotations[rotation as usize],
+            None => panic!("Attempted to get data for Id: {:?}", ty)
+        }
+    }
+
+    fn pieces(&self) -> Vec<Id> {
+        (0..self.data.len()).map(Id::from).collect()
+    }
+
+    fn spawn_rule(&self, id: Id) -> SpawnRule {
+        let (x, y) = self.max(id, Rotation::R0);
+        let n = i32!(x.max(y) + 1);
+
+        SpawnRule { offset: ((4 - n).div_euclid(2), 0), ..Default::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rotation_system::SRS;
+
+    #[test]
+    fn test_generic() {
+        let rs = Generic::new();
+        let srs = SRS::new();
+
+        for &id in &[Id::I, Id::T, Id::L, Id::J, Id::S, Id::Z] {
+            for r in Rotation::variants() {
+                let mut expected = srs.data(id, r).to_vec();
+                expected.sort_by_key(|&(x, y)| (y, x));
+                assert_eq!(rs.data(id, r), &expected[..]);
+            }
+        }
+
+        let x = Id::from_name("X").unwrap();
+        assert_eq!(rs.data(x, Rotation::R90), rs.data(x, Rotation::R0));
+        assert_eq!(rs.max(Id::from_name("I5").unwrap(), Rotation::R90), (2, 4));
+
+        // Pieces are centred about the tetromino spawn
+        assert_eq!(rs.spawn_rule(Id::T).offset, (0, 0));
+        assert_eq!(rs.spawn_rule(Id::O).offset, (1, 0));
+        assert_eq!(rs.spawn_rule(Id::from_name("I5").unwrap()).offset, (-1, 0));
+    }
+}
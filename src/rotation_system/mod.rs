@@ -6,6 +6,47 @@
 
 use block::{Id, Rotation};
 use std::cmp;
+use std::sync::Arc;
+
+/// The side a block is moved towards when it cannot be centred exactly.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Centre {
+    /// Round towards the left wall, as in the Guideline and TGM
+    Left,
+
+    /// Round towards the right wall, as in NES Tetris
+    Right
+}
+
+/// Specifies where and how a block is spawned.
+///
+/// The default rule spawns every block at the field spawn in `Rotation::R0`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpawnRule {
+    /// The offset applied to the spawn position
+    #[serde(default)]
+    pub offset: (i32, i32),
+
+    /// Centre the cells of the block on the spawn column instead of placing
+    /// the block there. The spawn column is the centre column of an
+    /// odd-width field, or the left of the two centre columns of an
+    /// even-width field.
+    #[serde(default)]
+    pub centre: Option<Centre>,
+
+    /// The initial orientation of the block
+    #[serde(default)]
+    pub rotation: Rotation,
+
+    /// The spawn row relative to the first visible row, replacing the row
+    /// of the field spawn. A negative row spawns within the hidden area.
+    #[serde(default)]
+    pub row: Option<i32>,
+
+    /// Should the block be moved down one row on spawn if unobstructed?
+    #[serde(default)]
+    pub drop: bool
+}
 
 /// The `RotationSystem` trait is implmented by all rotation systems.
 ///
@@ -13,12 +54,29 @@ use std::cmp;
 /// implement are the offset values for each of the main blocks
 /// `I, J, L, S, Z, T, O`.
 ///
-/// See the `srs.rs` file for an example.
+/// See the `srs.rs` file for an example, or `Custom` for a rotation system
+/// loaded at runtime.
 pub trait RotationSystem {
 
-    /// Returns a static array of offset values for the specified `Id`
-    /// and `Rotation`.
-    fn data(&self, ty: Id, rotation: Rotation) -> &'static [(usize, usize)];
+    /// Returns an array of offset values for the specified `Id` and
+    /// `Rotation`.
+    fn data(&self, ty: Id, rotation: Rotation) -> &[(usize, usize)];
+
+    /// Returns the pieces this rotation system has offsets for.
+    fn pieces(&self) -> Vec<Id> {
+        Id::variants().to_vec()
+    }
+
+    /// Returns the rule used when spawning the specified `Id`.
+    fn spawn_rule(&self, _id: Id) -> SpawnRule {
+        SpawnRule::default()
+    }
+
+    /// Returns the `(r, g, b)` colour of the specified `Id`, if this rotation
+    /// system defines one.
+    fn colour(&self, _id: Id) -> Option<(u8, u8, u8)> {
+        None
+    }
 
     /// Returns the minimum offset of the first piece in a block.
     ///
@@ -123,29 +181,29 @@ pub trait RotationSystem {
 }
 
 /// Generates all data fields for a `RotationSystem`. The only requirement is
-/// to implement the block offsets in static arrays.
+/// to implement the block offsets in static arrays and a `spawn_rule`
+/// function.
 ///
 /// This could work as a derive attribute probably, but that is extra work.
 macro_rules! rs_gen {
     ($id:ident) => {
         use block::{Id, Rotation};
-        use rotation_system::RotationSystem;
+        use rotation_system::{RotationSystem, SpawnRule, Centre};
+
+        use std::sync::Arc;
 
         #[allow(missing_docs)]
         pub struct $id;
 
-        // Each module gets its own static instance it can use
-        static __INSTANCE: $id = $id { };
-
         impl $id {
             /// Return a new instance
-            pub fn new() -> &'static $id {
-                &__INSTANCE
+            pub fn new() -> Arc<$id> {
+                Arc::new($id { })
             }
         }
 
         impl RotationSystem for $id {
-            fn data(&self, ty: Id, rotation: Rotation) -> &'static [(usize, usize)] {
+            fn data(&self, ty: Id, rotation: Rotation) -> &[(usize, usize)] {
                 match ty {
                     Id::I => &I[rotation as usize],
                     Id::T => &T[rotation as usize],
@@ -157,6 +215,10 @@ macro_rules! rs_gen {
                     _ => panic!("Attempted to get data for Id: {:?}", ty)
                 }
             }
+
+            fn spawn_rule(&self, id: Id) -> SpawnRule {
+                spawn_rule(id)
+            }
         }
     }
 }
@@ -165,11 +227,15 @@ pub use self::srs::SRS;
 pub use self::ars::ARS;
 pub use self::tengen::Tengen;
 pub use self::dtet::DTET;
+pub use self::custom::Custom;
+pub use self::generic::Generic;
 
 pub mod srs;
 pub mod ars;
 pub mod tengen;
 pub mod dtet;
+pub mod custom;
+pub mod generic;
 
 /// Factory function for constructing a rotation system from name.
 ///
@@ -181,12 +247,18 @@ pub mod dtet;
 ///  - `dtet`
 ///  - `arika`
 ///  - `tengen`
-pub fn new(name: &str) -> Result<&'static RotationSystem, String> {
+///  - `generic` (rotates any piece in the piece table)
+///
+/// Any name ending in `.json` is loaded from that file using
+/// `Custom::from_file`.
+pub fn new(name: &str) -> Result<Arc<RotationSystem>, String> {
     match name {
         "srs" => Ok(SRS::new()),
         "dtet" => Ok(DTET::new()),
         "ars" => Ok(ARS::new()),
         "tengen" => Ok(Tengen::new()),
+        "generic" => Ok(Generic::new()),
+        _ if name.ends_with(".json") => Ok(Arc::new(Custom::from_file(name)?)),
         _ => Err(format!("unknown rotation system: {}", name))
     }
 }
@@ -210,4 +282,31 @@ mod tests {
         assert_eq!((2, 0), rs.minp(Id::I, Rotation::R90));
         assert_eq!((0, 0), rs.minp(Id::Z, Rotation::R0));
     }
+
+    #[test]
+    fn test_spawn_rule() {
+        // Return the columns and top row of a spawned block
+        let spawn = |name, id, width| {
+            let field = Field::with_options(FieldOptions { width, ..Default::default() });
+            let block = Block::spawn(id, &field, None, rotation_system::new(name).unwrap());
+            let cells = block.cells();
+
+            let mut columns = cells.iter().map(|&(x, _)| x).collect::<Vec<_>>();
+            columns.sort();
+            columns.dedup();
+            (columns, cells.iter().map(|&(_, y)| y).min().unwrap())
+        };
+
+        // Pieces are centred, rounding left or right when they cannot be
+        assert_eq!(spawn("srs", Id::T, 10), (vec![3, 4, 5], 2));
+        assert_eq!(spawn("srs", Id::I, 10), (vec![3, 4, 5, 6], 3));
+        assert_eq!(spawn("srs", Id::O, 10), (vec![4, 5], 2));
+        assert_eq!(spawn("srs", Id::I, 9), (vec![2, 3, 4, 5], 3));
+        assert_eq!(spawn("tengen", Id::T, 10), (vec![4, 5, 6], 3));
+        assert_eq!(spawn("tengen", Id::I, 9), (vec![3, 4, 5, 6], 3));
+
+        // ARS and DTET spawn in the top visible rows without dropping
+        assert_eq!(spawn("ars", Id::T, 10), (vec![3, 4, 5], 3));
+        assert_eq!(spawn("dtet", Id::T, 9), (vec![3, 4, 5], 3));
+    }
 }
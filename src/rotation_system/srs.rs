@@ -2,6 +2,12 @@
 
 rs_gen!(SRS);
 
+/// Pieces spawn centred in the two rows above the visible field, rounding
+/// left, and drop one row if unobstructed as in the Guideline.
+fn spawn_rule(_id: Id) -> SpawnRule {
+    SpawnRule { centre: Some(Centre::Left), row: Some(-2), drop: true, ..Default::default() }
+}
+
 static I: [[(usize, usize); 4]; 4] = [
     [(0, 1), (1, 1), (2, 1), (3, 1)],
     [(2, 0), (2, 1), (2, 2), (2, 3)],
@@ -2,6 +2,12 @@
 
 rs_gen!(Tengen);
 
+/// Pieces spawn centred in the top row of the visible field, rounding right
+/// as in NES Tetris.
+fn spawn_rule(_id: Id) -> SpawnRule {
+    SpawnRule { centre: Some(Centre::Right), row: Some(0), ..Default::default() }
+}
+
 static I: [[(usize, usize); 4]; 4] = [
     [(0, 0), (1, 0), (2, 0), (3, 0)],
     [(1, 0), (1, 1), (1, 2), (1, 3)],
@@ -27,9 +27,12 @@
 //! "));
 //! ```
 
+use std::sync::Arc;
+
 use rotation_system::RotationSystem;
-use field::{Field, FieldOptions};
+use field::{Field, FieldOptions, Mask, SpawnRegion};
 use block::{self, Rotation, Block, BlockOptions};
+use randomizer::{self, SequenceRandomizer, Exhausted};
 
 use std::{fmt, iter};
 use std::cmp::PartialEq;
@@ -54,6 +57,9 @@ pub struct Schema {
 
     /// The current height of the schema
     pub height: usize,
+
+    /// The upcoming pieces, declared with a `queue:` line
+    pub queue: Vec<block::Id>,
 }
 
 /// Tests if two schema are equal.
@@ -120,6 +126,8 @@ impl Schema {
         for x in 0..field.width {
             for y in 0..field.height {
                 grid[y][x] = match (field.occupies((x, y)), block.occupies((x, y))) {
+                    (false, false) if field.mask((x, y)) == Mask::Blocked => '=',
+                    (false, false) if field.mask((x, y)) == Mask::Void => '.',
                     (true, true) => {
                         failure = true;
                         'X'
@@ -139,7 +147,8 @@ impl Schema {
             data: grid,
             height: grid_height,
             // Assume height > 1
-            width: grid_width
+            width: grid_width,
+            queue: Vec::new()
         };
 
         if failure {
@@ -155,6 +164,20 @@ impl Schema {
     /// between pairs of `|` characters. Leading and trailing whitespace is
     /// ignored so different strings may produce the same schema.
     ///
+    /// The following characters are recognised:
+    ///
+    /// ```text
+    /// ' ' an empty cell
+    /// '#' a filled cell
+    /// '@' a cell of the active block
+    /// '=' a permanently blocked cell
+    /// '.' a void cell, which is not part of the field
+    /// 'S' a cell of the spawn region of blocks, which is otherwise empty
+    /// ```
+    ///
+    /// A line starting with `queue:` declares the upcoming pieces, parsed
+    /// with `randomizer::parse_sequence`.
+    ///
     /// ## Examples
     /// ```text
     /// use tetrs::schema::Schema;
@@ -172,7 +195,16 @@ impl Schema {
     ///  assert_eq!(schema1, schema2); // True
     /// ```
     pub fn from_string(field: &str) -> Schema {
-        let grid = field.split('\n')
+        let (queue, field): (Vec<_>, Vec<_>) = field.split('\n')
+                                                    .map(|s| s.trim())
+                                                    .partition(|s| s.starts_with("queue:"));
+
+        let queue = queue.iter()
+                         .flat_map(|s| randomizer::parse_sequence(&s["queue:".len()..])
+                                           .expect("invalid queue"))
+                         .collect();
+
+        let grid = field.iter()
                         .map(|s| {
                             s.trim()
                              .chars()
@@ -192,10 +224,36 @@ impl Schema {
         Schema {
             data: grid,
             width: grid_width,
-            height: grid_height
+            height: grid_height,
+            queue
         }
     }
 
+    /// Construct a randomizer which plays the queue of this schema.
+    ///
+    /// Once the queue is exhausted the randomizer ends the game.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::schema::Schema;
+    ///
+    /// let schema = Schema::from_string("
+    ///     |  @@      |
+    ///     |   @@     |
+    ///     ------------
+    ///     queue: IO
+    /// ");
+    ///
+    /// let mut randomizer = schema.to_randomizer(1);
+    /// assert_eq!(randomizer.next(), block::Id::I);
+    /// assert_eq!(randomizer.next(), block::Id::O);
+    /// assert_eq!(randomizer.next(), block::Id::None);
+    /// ```
+    pub fn to_randomizer(&self, lookahead: usize) -> SequenceRandomizer {
+        SequenceRandomizer::new(lookahead, &self.queue, Exhausted::End)
+    }
+
     /// Constuct state objects from a given schema. This is slightly finicky
     /// and there are a few cases to consider.
     ///
@@ -228,25 +286,66 @@ impl Schema {
     /// ");
     /// let (field, block) = schema2.to_state(); // Okay
     /// ```
-    pub fn to_state(&self, rotation_system: &'static RotationSystem) -> (Field, Block) {
+    pub fn to_state(&self, rotation_system: Arc<RotationSystem>) -> (Field, Block) {
         let mut schema = self.clone();
+        let field = self.to_field();
+        let mut block = None;
 
+        for (y, x) in iproduct!(0..schema.height, 0..schema.width) {
+            if schema.data[y][x] == '@' {
+                block = Some(schema.match_block(&field, &rotation_system, (x, y)));
+            }
+        }
+
+        // Testing with no block is pointless
+        (field, block.expect("block is required in a schema"))
+    }
+
+    /// Construct a field from a given schema, ignoring any block.
+    ///
+    /// This allows masked fields to be specified as text. The spawn region
+    /// of the field is the smallest rectangle containing every 'S' cell.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::schema::Schema;
+    ///
+    /// let field = Schema::from_string("
+    ///     |..SSSS..|
+    ///     |.      .|
+    ///     |        |
+    ///     |==    ==|
+    ///     ----------
+    /// ").to_field();
+    ///
+    /// assert_eq!(field.spawn_region, Some(SpawnRegion { x: 2, y: 0, width: 4, height: 1 }));
+    /// assert_eq!(field.mask((0, 0)), Mask::Void);
+    /// assert_eq!(field.mask((0, 3)), Mask::Blocked);
+    /// ```
+    pub fn to_field(&self) -> Field {
         let mut field = Field::with_options(FieldOptions {
-                            height: schema.height,
-                            width: schema.width,
+                            height: self.height,
+                            width: self.width,
                             ..Default::default()
                         });
-        let mut block = None;
 
-        for (y, x) in iproduct!(0..schema.height, 0..schema.width) {
-            match schema.data[y][x] {
-                '@' => {
-                    block = Some(schema.match_block(&field, rotation_system, (x, y)));
-                },
+        let mut spawn = Vec::new();
+        for (y, x) in iproduct!(0..self.height, 0..self.width) {
+            match self.data[y][x] {
                 '#' => {
-                    field.data[y][x] = block::Id::I;
+                    field.set((x, y), block::Id::I);
+                },
+                '=' => {
+                    field.set_mask((x, y), Mask::Blocked);
                 },
-                ' ' => {
+                '.' => {
+                    field.set_mask((x, y), Mask::Void);
+                },
+                'S' => {
+                    spawn.push((x, y));
+                },
+                ' ' | '@' => {
                     ();
                 },
                 _ => {
@@ -255,8 +354,16 @@ impl Schema {
             }
         }
 
-        // Testing with no block is pointless
-        (field, block.expect("block is required in a schema"))
+        if !spawn.is_empty() {
+            let (left, right) = spawn.iter().map(|&(x, _)| x).minmax().into_option().unwrap();
+            let (top, bottom) = spawn.iter().map(|&(_, y)| y).minmax().into_option().unwrap();
+
+            field.spawn_region = Some(SpawnRegion {
+                x: i32!(left), y: i32!(top), width: right - left + 1, height: bottom - top + 1
+            });
+        }
+
+        field
     }
 
     // Return true if the specified x, y point is in the schema bounds and is
@@ -288,10 +395,10 @@ impl Schema {
     // If it is required for exact rotations, then we could add support for
     // rotation specification in the input string, but this adds complexity
     // and more rules which are not needed currently.
-    fn match_block(&mut self, field: &Field, rotation_system: &'static RotationSystem,
+    fn match_block(&mut self, field: &Field, rotation_system: &Arc<RotationSystem>,
                    (x, y): (usize, usize)) -> Block {
 
-        for (&ty, &ro) in iproduct!(block::Id::variants().iter(), Rotation::variants().iter()) {
+        for (&ty, &ro) in iproduct!(rotation_system.pieces().iter(), Rotation::variants().iter()) {
             let data = rotation_system.data(ty, ro);
             let (xo, yo) = rotation_system.minp(ty, ro);
 
@@ -306,13 +413,12 @@ impl Schema {
                     self.data[oy + b][ox + a] = ' ';
                 });
 
-                // (xo, yo) are not normalized based on the field so the block
-                // needs to be adjusted.
                 let block = Block::with_options(ty, &field, BlockOptions {
                     x: Some(i32!(ox)),
-                    y: Some(i32!(field.height - oy - 1)),
+                    y: Some(i32!(oy)),
                     rotation: ro,
-                    ..Default::default()
+                    rotation_system: rotation_system.clone(),
+                    scale: Some(1)
                 });
 
                 assert!(!block.collides(&field));
@@ -334,6 +440,12 @@ impl Schema {
                       .chars())
            .chain("|\n".chars()
                        .chain(iter::repeat('-').take(self.width + 2)))
+           .chain(if self.queue.is_empty() {
+                      String::new()
+                  }
+                  else {
+                      format!("\nqueue: {}", self.queue.iter().map(|id| id.name()).join(","))
+                  }.chars())
            .collect()
     }
 
@@ -360,7 +472,7 @@ impl fmt::Display for Schema {
 impl PartialEq for Schema {
     fn eq(&self, other: &Self) -> bool {
         // We can use an iterator here?
-        if self.width == other.width {
+        if self.width == other.width && self.queue == other.queue {
             self.truncate().data.as_slice() == other.truncate().data.as_slice()
         }
         else {
@@ -442,8 +554,60 @@ mod tests {
         assert_eq!(block.x, 2);
         assert_eq!(block.y, i32!(field.height - 2));
 
-        assert_eq!(field.data[field.height-1][0], block::Id::I);
-        assert_eq!(field.data[field.height-1][1], block::Id::I);
-        assert_eq!(field.data[field.height-2][1], block::Id::I);
+        assert_eq!(field.get((0, field.height-1)), block::Id::I);
+        assert_eq!(field.get((1, field.height-1)), block::Id::I);
+        assert_eq!(field.get((1, field.height-2)), block::Id::I);
+    }
+
+    #[test]
+    fn test_queue() {
+        let schema = Schema::from_string("
+                |  @       |
+                | @@@      |
+                ------------
+                queue: I5, X, I
+            ");
+
+        let queue = vec![block::Id::from_name("I5").unwrap(), block::Id::from_name("X").unwrap(),
+                         block::Id::I];
+        assert_eq!(schema.queue, queue);
+        assert_eq!(Schema::from_string(&schema.to_string()), schema);
+
+        let mut randomizer = schema.to_randomizer(1);
+        for &id in &queue {
+            assert_eq!(randomizer.next(), id);
+        }
+        assert_eq!(randomizer.next(), block::Id::None);
+    }
+
+    #[test]
+    fn test_masked_state() {
+        let schema = Schema::from_string("
+                |.  @  ...|
+                |. @@@  ..|
+                |=       =|
+                |=  ##   =|
+                -----------
+            ");
+
+        let (mut field, mut block) = schema.to_state(rotation_system::new("srs").unwrap());
+        assert_eq!(field.mask((8, 1)), Mask::Void);
+        assert_eq!(field.mask((0, 3)), Mask::Blocked);
+
+        // The block cannot move into the void cells
+        assert!(block.shift(&field, Direction::Left));
+        assert!(!block.shift(&field, Direction::Left));
+
+        block.shift_extend(&field, Direction::Down);
+        assert_eq!(Schema::from_state(&field, &block), Schema::from_string("
+                |.     ...|
+                |. @    ..|
+                |=@@@    =|
+                |=  ##   =|
+                -----------
+            "));
+
+        field.freeze(block);
+        assert_eq!(field.clear_lines(), 0);
     }
 }
@@ -22,6 +22,12 @@ pub struct Statistics {
 
     /// Total tetrises
     pub fours: u64,
+
+    /// The longest chain of successive clears
+    pub max_chain: u64,
+
+    /// Total inputs used in excess of the minimum required
+    pub finesse_faults: u64,
 }
 
 impl Statistics {
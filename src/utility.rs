@@ -40,6 +40,6 @@ mod tests {
         block.shift(&field, Direction::Down);
 
         // Non-reference `wk` is slightly annoying.
-        block.rotate_with_wallkick(&field, wk, Rotation::R90);
+        block.rotate_with_wallkick(&field, &*wk, Rotation::R90);
     }
 }
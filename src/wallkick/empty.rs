@@ -11,7 +11,7 @@ gen_wallkick!(Empty);
 
 impl Wallkick for Empty {
     #![allow(unused_variables)]
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)] {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
         static NO_WALLKICK: [(i32, i32); 1] = [(0, 0)];
         &NO_WALLKICK
     }
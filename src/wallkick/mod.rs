@@ -6,19 +6,18 @@
 //! User wallkicks can be implemented, and for the most part, only require
 //! the `Wallkick` trait to be implemented.
 
+use std::sync::Arc;
+
 use block::{Block, Rotation};
 use field::Field;
 
 /// The `Wallkick` trait must be implemented by all wallkicks.
 ///
 /// Since wallkicks deal with static data, they often do not require an actual
-/// instance and instead static references are preferred.
+/// instance. See `Table` for a wallkick loaded at runtime.
 pub trait Wallkick {
     /// Wallkick tests for the specified id and rotation.
     ///
-    /// Wallkick test values are expected to lie in a static array currently.
-    /// This may be changed in the future if something is more applicable.
-    ///
     /// ## Examples
     /// ```
     /// use tetrs::import::*;
@@ -34,20 +33,20 @@ pub trait Wallkick {
     ///     }
     /// }
     /// ```
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)];
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)];
 }
 
 macro_rules! gen_wallkick {
     ($wkid:ident) => {
+        use std::sync::Arc;
+
         #[allow(missing_docs)]
         pub struct $wkid;
 
-        static __INSTANCE: $wkid = $wkid {};
-
         impl $wkid {
-            /// Returns a static instance to this wallkick.
-            pub fn new() -> &'static $wkid {
-                &__INSTANCE
+            /// Returns a new instance of this wallkick.
+            pub fn new() -> Arc<$wkid> {
+                Arc::new($wkid {})
             }
         }
     }
@@ -59,6 +58,7 @@ pub use self::simple::Simple;
 pub use self::dtet::DTET;
 pub use self::tgm::TGM;
 pub use self::tgm3::TGM3;
+pub use self::table::Table;
 
 mod srs;
 mod empty;
@@ -66,6 +66,7 @@ mod simple;
 mod dtet;
 mod tgm;
 mod tgm3;
+pub mod table;
 
 /// Factory function for constructing rotation systems from name.
 ///
@@ -79,7 +80,10 @@ mod tgm3;
 ///  - `dtet`
 ///  - `tgm`
 ///  - `tgm3`
-pub fn new(name: &str) -> Result<&'static Wallkick, String> {
+///
+/// Any name ending in `.json` is loaded from that file using
+/// `Table::from_file`.
+pub fn new(name: &str) -> Result<Arc<Wallkick>, String> {
     match name {
         "srs" => Ok(SRS::new()),
         "empty" => Ok(Empty::new()),
@@ -87,6 +91,7 @@ pub fn new(name: &str) -> Result<&'static Wallkick, String> {
         "dtet" => Ok(DTET::new()),
         "tgm" => Ok(TGM::new()),
         "tgm3" => Ok(TGM3::new()),
+        _ if name.ends_with(".json") => Ok(Arc::new(Table::from_file(name)?)),
         _ => Err(format!("unknown wallkick: {}", name))
     }
 }
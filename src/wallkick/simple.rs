@@ -11,7 +11,7 @@ gen_wallkick!(Simple);
 
 impl Wallkick for Simple {
     #![allow(unused_variables)]
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)] {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
         static SIMPLE_WALLKICK: [(i32, i32); 3] = [(0, 0), (1, 0), (-1, 0)];
         &SIMPLE_WALLKICK
     }
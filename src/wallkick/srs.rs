@@ -12,7 +12,7 @@ gen_wallkick!(SRS);
 
 impl Wallkick for SRS {
     #[allow(unused_variables)]
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)] {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
         // O block does not have any special wallkick data.
         if block.id == block::Id::O {
             &RIGHT_JLSTZ[0][..1]
@@ -0,0 +1,219 @@
+//! A table-driven wallkick which is defined at runtime.
+//!
+//! A definition is a list of kicks. Each kick applies to a set of pieces
+//! rotating between two rotation states and gives the offsets to test. An
+//! empty piece list applies to every piece. The first kick which matches a
+//! rotation is used, so conditional kicks should be listed before any
+//! fallback. If no kick matches, only the trivial offset `(0, 0)` is tested.
+//!
+//! ```text
+//! {
+//!   "kicks": [
+//!     {
+//!       "pieces": ["I"],
+//!       "from": "R0",
+//!       "to": "R90",
+//!       "condition": "Grounded",
+//!       "tests": [[0, 0], [0, -1], [0, -2]]
+//!     },
+//!     {
+//!       "pieces": [],
+//!       "from": "R0",
+//!       "to": "R90",
+//!       "tests": [[0, 0], [1, 0], [-1, 0]]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use std::fs::File;
+use std::io::Read;
+use serde_json;
+
+use block::{Block, Id, Rotation};
+use field::Field;
+use wallkick::Wallkick;
+
+/// A requirement on the rotating block for a kick to be used.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Condition {
+    /// The kick is always used
+    #[default]
+    Always,
+
+    /// The kick is only used if the block is resting on the stack or floor
+    Grounded,
+
+    /// The kick is only used if the block is not resting on anything
+    Airborne
+}
+
+/// The definition of a single kick.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KickDefinition {
+    /// The pieces this kick applies to, by name (e.g. `"T"`)
+    #[serde(default)]
+    pub pieces: Vec<String>,
+
+    /// The rotation state being rotated from
+    pub from: Rotation,
+
+    /// The rotation state being rotated to
+    pub to: Rotation,
+
+    /// When this kick can be used
+    #[serde(default)]
+    pub condition: Condition,
+
+    /// The offsets to test in order
+    pub tests: Vec<(i32, i32)>
+}
+
+/// The serialized form of a `Table` wallkick.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Definition {
+    /// All kicks in priority order
+    pub kicks: Vec<KickDefinition>
+}
+
+/// A single parsed kick.
+struct Kick {
+    pieces: Vec<Id>,
+    from: Rotation,
+    to: Rotation,
+    condition: Condition,
+    tests: Vec<(i32, i32)>
+}
+
+/// A wallkick constructed from a `Definition`.
+pub struct Table {
+    kicks: Vec<Kick>
+}
+
+static NO_WALLKICK: [(i32, i32); 1] = [(0, 0)];
+
+impl Table {
+    /// Construct a wallkick from a definition.
+    ///
+    /// An error is returned if a piece is unknown or a kick has no tests.
+    pub fn from_definition(definition: Definition) -> Result<Table, String> {
+        let mut kicks = Vec::new();
+
+        for kick in definition.kicks {
+            let mut pieces = Vec::new();
+            for name in &kick.pieces {
+                match Id::from_name(name) {
+                    Some(id) => pieces.push(id),
+                    None => return Err(format!("unknown piece: {}", name))
+                }
+            }
+
+            if kick.tests.is_empty() {
+                return Err(format!("kick {:?} -> {:?} has no tests", kick.from, kick.to));
+            }
+
+            kicks.push(Kick {
+                pieces, from: kick.from, to: kick.to, condition: kick.condition, tests: kick.tests
+            });
+        }
+
+        Ok(Table { kicks })
+    }
+
+    /// Construct a wallkick from a JSON definition.
+    ///
+    /// ## Examples
+    /// ```
+    /// use tetrs::import::*;
+    /// use tetrs::wallkick::Table;
+    ///
+    /// let wallkick = Table::from_json(r#"{
+    ///     "kicks": [{ "from": "R0", "to": "R90", "tests": [[0, 0], [1, 0]] }]
+    /// }"#).unwrap();
+    ///
+    /// let field = Field::new();
+    /// let block = Block::new(block::Id::T, &field);
+    /// assert_eq!(wallkick.test(&block, &field, Rotation::R90), &[(0, 0), (1, 0)]);
+    /// assert_eq!(wallkick.test(&block, &field, Rotation::R270), &[(0, 0)]);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Table, String> {
+        let definition = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Table::from_definition(definition)
+    }
+
+    /// Load a wallkick from a JSON definition file.
+    pub fn from_file(filename: &str) -> Result<Table, String> {
+        let mut s = String::new();
+        File::open(filename).and_then(|mut f| f.read_to_string(&mut s))
+                            .map_err(|e| format!("{}: {}", filename, e))?;
+        Table::from_json(&s)
+    }
+}
+
+impl Wallkick for Table {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
+        let to = Rotation::from((block.r as usize + r as usize) % 4);
+        let grounded = block.collides_at_offset(field, (0, block.scale));
+
+        let kick = self.kicks.iter().find(|kick| {
+            kick.from == block.r && kick.to == to &&
+                (kick.pieces.is_empty() || kick.pieces.contains(&block.id)) &&
+                match kick.condition {
+                    Condition::Always => true,
+                    Condition::Grounded => grounded,
+                    Condition::Airborne => !grounded
+                }
+        });
+
+        match kick {
+            Some(kick) => &kick.tests,
+            None => &NO_WALLKICK
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallkick::SRS;
+
+    #[test]
+    fn test_table() {
+        let wallkick = Table::from_json(r#"{
+            "kicks": [
+                { "pieces": ["I"], "from": "R0", "to": "R90", "condition": "Grounded",
+                  "tests": [[0, 0], [0, -1]] },
+                { "from": "R0", "to": "R90", "tests": [[0, 0], [1, 0]] }
+            ]
+        }"#).unwrap();
+
+        let mut field = Field::new();
+        let mut block = Block::new(Id::I, &field);
+        assert_eq!(wallkick.test(&block, &field, Rotation::R90), &[(0, 0), (1, 0)]);
+
+        block.y = 20;
+        for x in 0..field.width {
+            field.set((x, 22), Id::O);
+        }
+        assert_eq!(wallkick.test(&block, &field, Rotation::R90), &[(0, 0), (0, -1)]);
+
+        // A table can reproduce a built-in wallkick
+        let kicks = Rotation::variants().into_iter().map(|r| KickDefinition {
+            pieces: vec!["T".to_string()],
+            from: r,
+            to: r.clockwise(),
+            condition: Condition::Always,
+            tests: SRS::new().test(&Block { id: Id::T, r, ..block.clone() }, &field, Rotation::R90).to_vec()
+        });
+
+        let table = Table::from_definition(Definition { kicks: kicks.collect() }).unwrap();
+        for r in Rotation::variants() {
+            let block = Block { id: Id::T, r, ..block.clone() };
+            assert_eq!(table.test(&block, &field, Rotation::R90),
+                       SRS::new().test(&block, &field, Rotation::R90));
+        }
+
+        assert!(Table::from_json(r#"{ "kicks": [{ "pieces": ["Q"], "from": "R0",
+                                     "to": "R90", "tests": [[0, 0]] }] }"#).is_err());
+    }
+}
@@ -16,7 +16,7 @@ static NONE_ROTATION: [(i32, i32); 1] = [
 
 impl Wallkick for TGM {
     #[allow(unused_variables)]
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)] {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
         if block.id == Id::I {
             return &NONE_ROTATION;
         }
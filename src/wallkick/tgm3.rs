@@ -50,7 +50,7 @@ static T_FLOORKICK_ROTATION: [(i32, i32); 2] = [
 ];
 
 impl Wallkick for TGM3 {
-    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &'static [(i32, i32)] {
+    fn test(&self, block: &Block, field: &Field, r: Rotation) -> &[(i32, i32)] {
         if block.id == Id::I {
             // Check if any field pieces exist beneath the I block. Wallkicks
             // are not allowed in mid-air.
@@ -105,6 +105,6 @@ impl Wallkick for TGM3 {
         }
 
         // Fallback to traditional TGM specification
-        wallkick::TGM::new().test(&block, &field, r)
+        wallkick::TGM.test(block, field, r)
     }
 }